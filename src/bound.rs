@@ -1,73 +1,483 @@
 use cargo_lock::Lockfile;
 use semver::{BuildMetadata, Comparator, Op, Prerelease, Version, VersionReq};
+use serde::{Deserialize, Serialize};
 use std::{
+    cmp::{Ordering as CmpOrdering, Reverse},
+    collections::{BTreeSet, HashMap, HashSet},
     mem::take,
-    ops::{Add, Sub},
-    u64,
+    ops::Add,
 };
 
 use crate::{
+    compat::DependencyKind,
     error::{ConstError, Result, UNSUPPORTED_SEMVER_OPERATOR},
+    get_config,
     provider::{ParsedVersion, Provider},
-    utils::CRATE_NAME,
+    utils::{edit_distance, CRATE_NAME},
 };
 
+// The most older versions of a dependent tried by `find_downgrade`
+const DOWNGRADE_CANDIDATES: usize = 10;
+
+// More names suggested by `check_in_lockfile` than this reads as a list to search rather
+// than a hint
+const MAX_SUGGESTIONS: usize = 3;
+
 // Get a bound for the crate based on the dependent's requirements as well as all versions
 // of that crate
 // It combines all the requirements from direct dependents into one single range and then
 // matches that range against actual versions of the crate.
 // The method for finding it is simple and works in most cases, but it doesn't:-
-// - Take into account disjoint dependencies, i.e when there are two disjoint versions
-//   of the crate being used by two dependents that don't interact, the approach
-//   here flags them as incompatible.
+// - Take into account disjoint dependencies by itself, i.e when there are two disjoint
+//   versions of the crate being used by two dependents that don't interact, the approach
+//   here flags them as incompatible, callers split such dependents up by the version the
+//   lockfile resolved them to with `filter_locked_to` and find a bound for each.
 // - Attempt to bump other dependents down in order to find more compatitible versions
 //   this would end up changing the versions of other dependencies, would be slower to
-//   find and would be harder on crates.io, so if at all it is added it would be gated.
+//   find and would be harder on crates.io, so it is only tried by `find_downgrade`,
+//   behind --allow-downgrade.
+
+// The resolved window into the sorted versions of the crate along with the dependents
+// and requirements that were combined to find it
+pub struct PackedBound {
+    // The positions in `versions` of the first version within the bound and of the one
+    // after the last
+    pub range: (usize, usize),
+    pub versions: Vec<ParsedVersion>,
+    pub dependents: Vec<((String, String), VersionReq)>,
+    // The bound merged from the requirements and the positions of the dependents that set
+    // its lower and upper ends
+    pub bound: Bound,
+    pub lower_index: usize,
+    pub upper_index: usize,
+}
+
+impl PackedBound {
+    // The versions that fall within the bound, oldest first
+    pub fn in_range(&self) -> &[ParsedVersion] {
+        &self.versions[self.range.0..self.range.1]
+    }
+}
 
 pub fn find_packed_bound(
     client: &Provider,
     crate_to_find: &str,
-    lock: &Lockfile,
-) -> Result<((usize, usize), Vec<ParsedVersion>)> {
-    // Find all dependent packages that depend on `crate_to_find`, picking out the name and version
-    let dependents = lock
+    dependents: Vec<(String, String)>,
+) -> Result<PackedBound> {
+    let merged_bound = merge_dependents(client, crate_to_find, dependents)?;
+
+    match_versions(client, crate_to_find, merged_bound)
+}
+
+// A dependent moved to an older version of itself
+pub struct Downgrade {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+// Look for an older version of one of the conflicting dependents whose requirement, or
+// lack of one, lets the crate resolve. Each dependent is tried alone, newest older
+// version first and at most DOWNGRADE_CANDIDATES of them, as every try fetches the
+// dependencies of that version
+pub fn find_downgrade(
+    client: &Provider,
+    crate_to_find: &str,
+    dependents: &[(String, String)],
+    conflicting: &[(String, String)],
+) -> Result<Option<(Downgrade, PackedBound)>> {
+    for (name, current) in conflicting {
+        let Ok(current_version) = Version::parse(current) else {
+            continue;
+        };
+
+        let mut older = client
+            .get_versions(name)?
+            .versions
+            .into_iter()
+            .filter(|version| version.num.lt(&current_version) && !version.yanked)
+            .collect::<Vec<_>>();
+
+        older.sort();
+
+        for candidate in older.iter().rev().take(DOWNGRADE_CANDIDATES) {
+            let to = candidate.num.to_string();
+
+            let depends = client
+                .get_dependencies(name, &to)?
+                .dependencies
+                .iter()
+                .any(|dependency| dependency.crate_id.eq(crate_to_find));
+
+            // A version without the dependency drops its requirement altogether
+            let trial = dependents
+                .iter()
+                .filter(|dependent| depends || dependent.0.ne(name) || dependent.1.ne(current))
+                .map(
+                    |dependent| match dependent.0.eq(name) && dependent.1.eq(current) {
+                        true => (name.clone(), to.clone()),
+                        false => dependent.clone(),
+                    },
+                )
+                .collect::<Vec<_>>();
+
+            if trial.is_empty() {
+                continue;
+            }
+
+            match find_packed_bound(client, crate_to_find, trial) {
+                Ok(packed_bound) => {
+                    let downgrade = Downgrade {
+                        name: name.clone(),
+                        from: current.clone(),
+                        to,
+                    };
+
+                    return Ok(Some((downgrade, packed_bound)));
+                }
+                Err(error) if error.is_unsatisfiable() => continue,
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+// Resolve every crate the lockfile has as a dependency, in order of name, with the
+// result for each kept separately so one failing doesn't stop the rest. The client's
+// cache is shared between them, so dependents common to several crates are fetched once
+pub fn resolve_all(client: &Provider, lock: &Lockfile) -> Vec<(String, Result<PackedBound>)> {
+    let crate_names = lock
         .packages
         .iter()
-        .filter(|package| {
-            package
+        .flat_map(|package| package.dependencies.iter())
+        .map(|dependency| dependency.name.as_str().to_string())
+        .collect::<BTreeSet<_>>();
+
+    let index = DependentIndex::new(lock);
+
+    crate_names
+        .into_iter()
+        .map(|crate_name| {
+            let dependents = index.dependents_of(&crate_name).to_vec();
+
+            let result = find_packed_bound(client, &crate_name, dependents);
+
+            (crate_name, result)
+        })
+        .collect()
+}
+
+// Everything the bound merged for a crate depends on, which a cached bound is stored
+// under. The requirements of a published version never change so the dependents' names
+// and versions stand in for them
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ResolutionKey {
+    pub crate_name: String,
+    pub dependents: Vec<(String, String)>,
+    pub kind: DependencyKind,
+    // Sorted and without repeats, so the same selection is always the same key
+    pub features: Vec<String>,
+    pub all_features: bool,
+    pub no_default_features: bool,
+    pub package: Option<String>,
+    // Whether the caret, tilde and wildcard upper ends leave out prereleases
+    pub pre: bool,
+}
+
+// Same as `merge_dependents` but reuses the merged bound from an earlier run when nothing
+// in the key has changed since
+pub fn merge_dependents_cached(client: &Provider, key: ResolutionKey) -> Result<MergedBound> {
+    if let Some(merged_bound) = client.get_merged_bound(&key) {
+        return Ok(merged_bound);
+    }
+
+    let merged_bound = merge_dependents(client, &key.crate_name, key.dependents.clone())?;
+
+    client.set_merged_bound(&key, &merged_bound);
+
+    Ok(merged_bound)
+}
+
+// Raise the lower end of the bound to `at_least` if it isn't already above it, failing
+// if the upper end set by the dependents is below it
+pub fn raise_lower_bound(
+    crate_to_find: &str,
+    merged_bound: &mut MergedBound,
+    at_least: &Version,
+) -> Result<()> {
+    let Bound { lower, upper } = &mut merged_bound.bound;
+
+    if !upper.admits(at_least) {
+        let (dependent, version_req) = merged_bound.dependents[merged_bound.upper_index].clone();
+
+        return Err(ConstError::AtLeastAboveBoundError {
+            crate_name: crate_to_find.to_string(),
+            crate_version: at_least.to_string(),
+            dependent: (dependent, version_req),
+        });
+    }
+
+    if at_least.gt(&lower.version) {
+        *lower = Range {
+            version: at_least.clone(),
+            inclusive: true,
+        };
+    }
+
+    Ok(())
+}
+
+// The bound every dependent agrees on along with the positions of the dependents that set
+// its lower and upper ends
+#[derive(Deserialize, Serialize)]
+pub struct MergedBound {
+    pub bound: Bound,
+    pub lower_index: usize,
+    pub upper_index: usize,
+    pub dependents: Vec<((String, String), VersionReq)>,
+}
+
+// Find all dependent packages that depend on `crate_to_find`, picking out the name and version
+pub fn find_dependents(crate_to_find: &str, lock: &Lockfile) -> Vec<(String, String)> {
+    DependentIndex::new(lock)
+        .dependents_of(crate_to_find)
+        .to_vec()
+}
+
+// The dependents of every crate in the lockfile, found in a single pass over its packages
+// so looking up several crates doesn't scan a large lockfile once for each
+pub struct DependentIndex {
+    dependents: HashMap<String, Vec<(String, String)>>,
+}
+
+impl DependentIndex {
+    pub fn new(lock: &Lockfile) -> DependentIndex {
+        let mut dependents = HashMap::<String, Vec<(String, String)>>::new();
+
+        for package in &lock.packages {
+            if package.name.as_str().eq(CRATE_NAME) {
+                continue;
+            }
+
+            let dependent = (
+                package.name.as_str().to_string(),
+                package.version.to_string(),
+            );
+
+            // A package can depend on two versions of a crate, it is still one dependent
+            let names = package
                 .dependencies
                 .iter()
-                .find(|dependency| dependency.name.as_str().eq(crate_to_find))
-                .is_some()
-                && package.name.as_str().ne(CRATE_NAME)
+                .map(|dependency| dependency.name.as_str())
+                .collect::<HashSet<_>>();
+
+            for name in names {
+                dependents
+                    .entry(name.to_string())
+                    .or_default()
+                    .push(dependent.clone());
+            }
+        }
+
+        for list in dependents.values_mut() {
+            list.sort_by(cmp_dependents);
+        }
+
+        DependentIndex { dependents }
+    }
+
+    // The name and version of the packages that depend on `crate_to_find`, sorted
+    pub fn dependents_of(&self, crate_to_find: &str) -> &[(String, String)] {
+        self.dependents
+            .get(crate_to_find)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+// Order dependents by name, then by version as semver orders it so 0.9.0 comes before
+// 0.10.0, falling back to the text for a version that doesn't parse
+pub fn cmp_dependents(first: &(String, String), second: &(String, String)) -> CmpOrdering {
+    first.0.cmp(&second.0).then_with(|| {
+        match (Version::parse(&first.1), Version::parse(&second.1)) {
+            (Ok(first), Ok(second)) => first.cmp(&second),
+            _ => first.1.cmp(&second.1),
+        }
+    })
+}
+
+// Fail unless the crate is somewhere in the lockfile, as a package or as a dependency of
+// one, suggesting the closest names in it in case of a typo
+pub fn check_in_lockfile(crate_to_find: &str, lock: &Lockfile) -> Result<()> {
+    let names = lock
+        .packages
+        .iter()
+        .flat_map(|package| {
+            std::iter::once(package.name.as_str()).chain(
+                package
+                    .dependencies
+                    .iter()
+                    .map(|dependency| dependency.name.as_str()),
+            )
+        })
+        .collect::<BTreeSet<_>>();
+
+    if names.contains(crate_to_find) {
+        return Ok(());
+    }
+
+    // Further than a third of the name apart is more likely another crate than a typo
+    let max_distance = (crate_to_find.chars().count() / 3).max(1);
+
+    let mut close_names = names
+        .into_iter()
+        .map(|name| (edit_distance(crate_to_find, name), name))
+        .filter(|(distance, _)| distance.le(&max_distance))
+        .collect::<Vec<_>>();
+
+    // Names as close as each other are listed alphabetically, so the output is stable
+    close_names.sort();
+
+    let suggestions = close_names
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, name)| name.to_string())
+        .collect();
+
+    Err(ConstError::NotInDependencyGraphError {
+        crate_name: crate_to_find.to_string(),
+        suggestions,
+    })
+}
+
+// Get the versions of the crate in the lockfile, oldest first
+pub fn find_locked_versions(crate_to_find: &str, lock: &Lockfile) -> Vec<Version> {
+    let mut versions = lock
+        .packages
+        .iter()
+        .filter(|package| package.name.as_str().eq(crate_to_find))
+        .map(|package| package.version.clone())
+        .collect::<Vec<_>>();
+
+    versions.sort();
+    versions.dedup();
+
+    versions
+}
+
+// Keep the dependents the lockfile resolved to `locked` for the crate
+pub fn filter_locked_to(
+    dependents: Vec<(String, String)>,
+    lock: &Lockfile,
+    crate_to_find: &str,
+    locked: &Version,
+) -> Vec<(String, String)> {
+    let locked_to = lock
+        .packages
+        .iter()
+        .filter(|package| {
+            package.dependencies.iter().any(|dependency| {
+                dependency.name.as_str().eq(crate_to_find) && dependency.version.eq(locked)
+            })
         })
         .map(|package| {
             (
                 package.name.as_str().to_string(),
                 package.version.to_string(),
             )
-        });
+        })
+        .collect::<HashSet<_>>();
+
+    dependents
+        .into_iter()
+        .filter(|dependent| locked_to.contains(dependent))
+        .collect()
+}
+
+// Get every package in the lockfile that `root` depends on directly or indirectly,
+// including `root` itself
+pub fn find_reachable(root: &str, lock: &Lockfile) -> HashSet<(String, String)> {
+    let packages = lock
+        .packages
+        .iter()
+        .map(|package| {
+            (
+                (package.name.as_str(), package.version.to_string()),
+                package,
+            )
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut pending = lock
+        .packages
+        .iter()
+        .filter(|package| package.name.as_str().eq(root))
+        .collect::<Vec<_>>();
+
+    let mut reachable = HashSet::new();
+
+    while let Some(package) = pending.pop() {
+        let key = (
+            package.name.as_str().to_string(),
+            package.version.to_string(),
+        );
+
+        if !reachable.insert(key) {
+            continue;
+        }
+
+        pending.extend(package.dependencies.iter().filter_map(|dependency| {
+            packages.get(&(dependency.name.as_str(), dependency.version.to_string()))
+        }));
+    }
+
+    reachable
+}
+
+// A dependent's name and version, the bound of its requirement and the requirement itself
+pub type DependentConstraint = ((String, String), Bound, VersionReq);
 
+// Get the requirement each dependent places on the crate along with its bound, sorted by
+// the dependent's name and version
+pub fn collect_constraints(
+    client: &Provider,
+    crate_to_find: &str,
+    dependents: Vec<(String, String)>,
+) -> Result<Vec<DependentConstraint>> {
     // Find all the dependency constraints set by the dependents, picking out the name, version
     // and constraint for that crate
-    let dependent_constraints = dependents
-        .map(|mut some_crate| {
-            let result = client.get_dependencies(&some_crate.0, &some_crate.1);
+    let fetched = client.get_dependencies_of_all(&dependents);
 
+    let dependent_constraints = dependents
+        .into_iter()
+        .zip(fetched)
+        .map(|(mut some_crate, result)| {
             let parsed_dependencies = result?;
 
-            let parsed_dependency = parsed_dependencies
+            let mut parsed_dependencies = parsed_dependencies
                 .dependencies
                 .into_iter()
-                .find(|parsed_dependency| parsed_dependency.crate_id.eq(crate_to_find))
-                .ok_or_else(|| ConstError::DependencyMismatchFromCargoLock {
+                .filter(|parsed_dependency| parsed_dependency.crate_id.eq(crate_to_find))
+                .peekable();
+
+            if parsed_dependencies.peek().is_none() {
+                return Err(ConstError::DependencyMismatchFromCargoLock {
                     dependency: crate_to_find.to_string(),
                     crate_name: take(&mut some_crate.0), // We use can take because we short circuit below
                     crate_version: take(&mut some_crate.1),
-                })?;
+                });
+            }
+
+            // A dependent that only has the crate as another kind of dependency is left out
+            let kind = get_config().kind;
 
-            Ok((some_crate, parsed_dependency))
+            Ok(parsed_dependencies
+                .find(|parsed_dependency| kind.includes(&parsed_dependency.kind))
+                .map(|parsed_dependency| (some_crate, parsed_dependency)))
         })
+        .filter_map(Result::transpose)
         .collect::<Result<Vec<_>>>()?;
 
     if dependent_constraints.is_empty() {
@@ -109,112 +519,68 @@ pub fn find_packed_bound(
         })
         .collect::<Result<Vec<_>>>()?;
 
-    let first = &dependent_constraints.first().unwrap().1;
+    // The endpoints picked below and the order dependents are reported in both follow the
+    // order here, so it is fixed rather than left to the lockfile
+    dependent_constraints.sort_by(|a, b| cmp_dependents(&a.0, &b.0));
+
+    Ok(dependent_constraints)
+}
+
+// Combine the requirements of all the dependents into a single bound
+pub fn merge_dependents(
+    client: &Provider,
+    crate_to_find: &str,
+    dependents: Vec<(String, String)>,
+) -> Result<MergedBound> {
+    let dependent_constraints = collect_constraints(client, crate_to_find, dependents)?;
+
+    merge_constraints(crate_to_find, dependent_constraints)
+}
 
-    let (lower_range, upper_range) = (
-        (&first.lower.version, first.lower.inclusive),
-        (&first.upper.version, first.upper.inclusive),
-    );
+// Combine the bounds of the requirements into one, there must be at least one
+pub fn merge_constraints(
+    crate_to_find: &str,
+    mut dependent_constraints: Vec<DependentConstraint>,
+) -> Result<MergedBound> {
+    let first = dependent_constraints.first().unwrap().1.clone();
 
     let mut upper_index = 0;
 
     let mut lower_index = 0;
 
-    // Find the overlap between all bounds or find the index with the first conflict
-    let result: std::result::Result<((&Version, bool), (&Version, bool)), usize> =
-        dependent_constraints.iter().skip(1).enumerate().try_fold(
-            (lower_range, upper_range),
-            |mut value_1, value_2| {
-                if contains_from_lower(value_1.0, &value_2.1 .1.lower)
-                    .eq(&Ordering::ContainsFromLower)
-                {
-                    lower_index = value_2.0.add(1);
-                    value_1.0 = (&value_2.1 .1.lower.version, value_2.1 .1.lower.inclusive);
-                }
+    // Find the overlap between all bounds or find the index with the first conflict, an end
+    // that changed was set by the dependent just merged in
+    let result: std::result::Result<Bound, usize> = dependent_constraints
+        .iter()
+        .enumerate()
+        .skip(1)
+        .try_fold(first, |merged, (index, constraint)| {
+            let next = merged.intersect(&constraint.1).map_err(|_| index)?;
 
-                if contains_from_upper(value_1.1, &value_2.1 .1.upper)
-                    .eq(&Ordering::ContainsFromUpper)
-                {
-                    upper_index = value_2.0.add(1);
-                    value_1.1 = (&value_2.1 .1.upper.version, value_2.1 .1.upper.inclusive);
-                }
+            if next.lower.ne(&merged.lower) {
+                lower_index = index;
+            }
 
-                if contains_from_upper(value_1.0, value_1.1).eq(&Ordering::ContainsFromUpper) {
-                    return Err(value_2.0.add(1));
-                }
+            if next.upper.ne(&merged.upper) {
+                upper_index = index;
+            }
 
-                Ok(value_1)
-            },
-        );
+            Ok(next)
+        });
 
     match result {
-        // At this point bound.lower <= bound.upper now we just have to make sure that
-        // that bound matches one or more actual versions
         Ok(bound) => {
-            let mut versions = client.get_versions(crate_to_find)?.versions;
-
-            versions.sort();
-
-            let lower = match versions.binary_search_by(|version| version.num.cmp(&bound.0 .0)) {
-                Ok(value) => {
-                    if bound.0 .1 {
-                        value
-                    } else {
-                        value.add(1)
-                    }
-                }
-                Err(value) => value,
-            };
-
-            let lower = isize::try_from(lower).unwrap();
-
-            let upper = match versions.binary_search_by(|version| version.num.cmp(&bound.1 .0)) {
-                Ok(value) => {
-                    let value = isize::try_from(value).unwrap();
-                    if bound.1 .1 {
-                        value
-                    } else {
-                        value.sub(1)
-                    }
-                }
-                Err(value) => {
-                    // We convert to isize so we can go below 0.
-                    isize::try_from(value).unwrap().sub(1)
-                }
-            };
-
-            if lower.gt(&upper) {
-                if lower_index.eq(&upper_index) {
-                    let bound = dependent_constraints
-                        .get_mut(usize::from(lower_index))
-                        .unwrap();
-
-                    Err(ConstError::UnsatisfiableSingleDependentError {
-                        crate_name: crate_to_find.to_string(),
-                        dependent: (take(&mut bound.0), take(&mut bound.2)),
-                    })
-                } else {
-                    let lower = (
-                        take(&mut dependent_constraints.get_mut(lower_index).unwrap().0),
-                        take(&mut dependent_constraints.get_mut(lower_index).unwrap().2),
-                    );
-                    let upper = (
-                        take(&mut dependent_constraints.get_mut(upper_index).unwrap().0),
-                        take(&mut dependent_constraints.get_mut(upper_index).unwrap().2),
-                    );
-
-                    Err(ConstError::UnsatisfiableBoundDependentsError {
-                        crate_name: crate_to_find.to_string(),
-                        lower,
-                        upper,
-                    })
-                }
-            } else {
-                let lower = usize::try_from(lower).unwrap();
-                let upper = usize::try_from(upper).unwrap();
+            let dependents = dependent_constraints
+                .into_iter()
+                .map(|(dependent, _, version_req)| (dependent, version_req))
+                .collect();
 
-                Ok(((lower, upper), versions))
-            }
+            Ok(MergedBound {
+                bound,
+                lower_index,
+                upper_index,
+                dependents,
+            })
         }
         // The last dependent which we tried to resolve their requirement caused the solution to
         // be unsatisfiable, we find all the dependents that would make it as such
@@ -232,10 +598,8 @@ pub fn find_packed_bound(
                 .enumerate()
                 .filter(|(position, _)| position.ne(&index))
             {
-                if contains_from_upper(&value.1 .1.lower, &bound.upper)
-                    .eq(&Ordering::ContainsFromUpper)
-                    || contains_from_lower(&value.1 .1.upper, &bound.lower)
-                        .eq(&Ordering::ContainsFromLower)
+                if is_empty(&value.1 .1.lower, &bound.upper)
+                    || is_empty(&bound.lower, &value.1 .1.upper)
                 {
                     unmet.push((take(&mut value.1 .0), take(&mut value.1 .2)));
                 }
@@ -252,65 +616,295 @@ pub fn find_packed_bound(
     }
 }
 
-#[derive(Clone)]
+// What it would take to satisfy the dependents when they don't all overlap, the most of
+// them that agree on a range and the others left out of it
+pub struct Resolution {
+    pub merged_bound: MergedBound,
+    // The dependents outside the range, each with whether its requirement only takes in
+    // versions below it, so a newer version of the dependent is needed, rather than above
+    pub left_out: Vec<((String, String), VersionReq, bool)>,
+}
+
+// Find the largest group of dependents whose bounds overlap on one of `versions`. Bounds
+// on a line that overlap in pairs all overlap together, so for each dependent it is enough
+// to gather the ones that overlap it and start no higher, the largest of these groups is
+// the largest of all. Groups whose range has no version in it are only picked when no group
+// has one, and the first found is kept when several are as large
+pub fn suggest_resolution(
+    crate_to_find: &str,
+    dependent_constraints: Vec<DependentConstraint>,
+    versions: &[Version],
+) -> Result<Resolution> {
+    let group_of = |constraint: &DependentConstraint| {
+        let mut overlap = constraint.1.clone();
+
+        let group = dependent_constraints
+            .iter()
+            .enumerate()
+            .filter(|(_, other)| {
+                match constraint.1.intersect(&other.1) {
+                    Ok(pair) if pair.lower.eq(&constraint.1.lower) => {
+                        // Overlapping in pairs, so they overlap with the rest of the group
+                        if let Ok(narrowed) = overlap.intersect(&other.1) {
+                            overlap = narrowed;
+                        }
+                        true
+                    }
+                    _ => false,
+                }
+            })
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+
+        let published = versions.iter().any(|version| overlap.contains(version));
+
+        (group, published)
+    };
+
+    let (largest, _) = dependent_constraints
+        .iter()
+        .map(group_of)
+        .min_by_key(|(group, published)| Reverse((*published, group.len())))
+        .unwrap_or_default();
+
+    let (group, left_out): (Vec<_>, Vec<_>) = dependent_constraints
+        .into_iter()
+        .enumerate()
+        .partition(|(position, _)| largest.contains(position));
+
+    let merged_bound = merge_constraints(
+        crate_to_find,
+        group
+            .into_iter()
+            .map(|(_, constraint)| constraint)
+            .collect(),
+    )?;
+
+    let left_out = left_out
+        .into_iter()
+        .map(|(_, (dependent, bound, version_req))| {
+            let below = is_empty(&merged_bound.bound.lower, &bound.upper);
+
+            (dependent, version_req, below)
+        })
+        .collect();
+
+    Ok(Resolution {
+        merged_bound,
+        left_out,
+    })
+}
+
+// One step of the merge, the bound so far once the dependent's bound was applied or `None`
+// if the two didn't overlap
+pub struct MergeStep<'a> {
+    pub constraint: &'a DependentConstraint,
+    pub merged: Option<Bound>,
+}
+
+// Replay the merge done in `merge_dependents` keeping every intermediate bound, stopping at
+// the first dependent that doesn't overlap with the rest
+pub fn trace_merge(dependent_constraints: &[DependentConstraint]) -> Vec<MergeStep<'_>> {
+    let mut steps = Vec::new();
+
+    let mut merged: Option<Bound> = None;
+
+    for constraint in dependent_constraints {
+        let next = match merged {
+            None => Some(constraint.1.clone()),
+            Some(current) => current.intersect(&constraint.1).ok(),
+        };
+
+        let conflict = next.is_none();
+
+        steps.push(MergeStep {
+            constraint,
+            merged: next.clone(),
+        });
+
+        if conflict {
+            break;
+        }
+
+        merged = next;
+    }
+
+    steps
+}
+
+// At this point bound.lower <= bound.upper now we just have to make sure that
+// that bound matches one or more actual versions
+pub fn match_versions(
+    client: &Provider,
+    crate_to_find: &str,
+    merged_bound: MergedBound,
+) -> Result<PackedBound> {
+    let MergedBound {
+        bound,
+        lower_index,
+        upper_index,
+        dependents: mut dependent_constraints,
+    } = merged_bound;
+
+    let mut versions = client.get_versions(crate_to_find)?.versions;
+
+    // Sorting by the total order keeps versions that only differ in build metadata next to
+    // each other, in the same order every run, and searching by precedence puts them on the
+    // same side of either end
+    versions.sort();
+
+    let (lower, upper) = find_range(&versions, &bound);
+
+    if lower.ge(&upper) {
+        if lower_index.eq(&upper_index) {
+            let bound = dependent_constraints.get_mut(lower_index).unwrap();
+
+            Err(ConstError::UnsatisfiableSingleDependentError {
+                crate_name: crate_to_find.to_string(),
+                dependent: (take(&mut bound.0), take(&mut bound.1)),
+            })
+        } else {
+            let lower = (
+                take(&mut dependent_constraints.get_mut(lower_index).unwrap().0),
+                take(&mut dependent_constraints.get_mut(lower_index).unwrap().1),
+            );
+            let upper = (
+                take(&mut dependent_constraints.get_mut(upper_index).unwrap().0),
+                take(&mut dependent_constraints.get_mut(upper_index).unwrap().1),
+            );
+
+            Err(ConstError::UnsatisfiableBoundDependentsError {
+                crate_name: crate_to_find.to_string(),
+                lower,
+                upper,
+            })
+        }
+    } else {
+        Ok(PackedBound {
+            range: (lower, upper),
+            versions,
+            dependents: dependent_constraints,
+            bound,
+            lower_index,
+            upper_index,
+        })
+    }
+}
+
+// The position of the first version within the bound in the sorted versions and the one
+// after the last, both the same when none are within it
+fn find_range(versions: &[ParsedVersion], bound: &Bound) -> (usize, usize) {
+    let lower = versions.partition_point(|version| {
+        match version.num.cmp_precedence(&bound.lower.version) {
+            CmpOrdering::Less => true,
+            CmpOrdering::Equal => !bound.lower.inclusive,
+            CmpOrdering::Greater => false,
+        }
+    });
+
+    // One past the last version within the bound
+    let upper = versions.partition_point(|version| bound.upper.admits(&version.num));
+
+    (lower, upper.max(lower))
+}
+
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Range {
     pub version: Version,
     pub inclusive: bool,
 }
 
 impl<'a> From<&'a Range> for (&'a Version, bool) {
-    fn from<'b>(value: &'b Range) -> (&'b Version, bool) {
+    fn from(value: &'a Range) -> (&'a Version, bool) {
         (&value.version, value.inclusive)
     }
 }
 
-// std::cmp::Ordering could be used but the
-// equals case(and then taking into account the is_inclusive case)
-// could very easily be a source of confusion and at that
-// point stops being analogical to it, so this is used instead
-#[derive(PartialEq, Eq)]
-pub enum Ordering {
-    ContainsFromLower,
-    ContainsFromUpper,
-    Equal,
+// The upper end of a bound, left open by requirements such as >=1.2 that only set a
+// lower end
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BoundEnd {
+    Unbounded,
+    Inclusive(Version),
+    Exclusive(Version),
 }
 
-fn contains_from_lower<'c, 'd, T, R>(a: T, b: R) -> Ordering
-where
-    T: Into<(&'c Version, bool)>,
-    R: Into<(&'d Version, bool)>,
-{
-    let a = a.into();
-    let b = b.into();
-    match a.0.cmp_precedence(&b.0) {
-        std::cmp::Ordering::Less => Ordering::ContainsFromLower,
-        std::cmp::Ordering::Equal => {
-            if !b.1 && a.1 {
-                Ordering::ContainsFromLower
-            } else if b.1 && !a.1 {
-                Ordering::ContainsFromUpper
-            } else {
-                Ordering::Equal
+impl BoundEnd {
+    // The version at the end and whether it is included, `None` if the end is open
+    pub fn as_range(&self) -> Option<(&Version, bool)> {
+        match self {
+            BoundEnd::Unbounded => None,
+            BoundEnd::Inclusive(version) => Some((version, true)),
+            BoundEnd::Exclusive(version) => Some((version, false)),
+        }
+    }
+
+    // Whether the version is at or below the end
+    pub fn admits(&self, version: &Version) -> bool {
+        match self.as_range() {
+            None => true,
+            Some((end, inclusive)) => match version.cmp_precedence(end) {
+                CmpOrdering::Less => true,
+                CmpOrdering::Equal => inclusive,
+                CmpOrdering::Greater => false,
+            },
+        }
+    }
+
+    // Whether this end leaves out versions the other one lets in, an open end is above
+    // every other
+    pub fn is_below(&self, other: &BoundEnd) -> bool {
+        match (self.as_range(), other.as_range()) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some((end, inclusive)), Some((other_end, other_inclusive))) => {
+                match end.cmp_precedence(other_end) {
+                    CmpOrdering::Less => true,
+                    CmpOrdering::Equal => !inclusive && other_inclusive,
+                    CmpOrdering::Greater => false,
+                }
             }
         }
-        std::cmp::Ordering::Greater => Ordering::ContainsFromUpper,
     }
 }
 
-fn contains_from_upper<'c, 'd, T, R>(a: T, b: R) -> Ordering
+// Whether no version lies between the two ends
+fn is_empty(lower: &Range, upper: &BoundEnd) -> bool {
+    match upper.as_range() {
+        None => false,
+        Some((end, inclusive)) => match lower.version.cmp_precedence(end) {
+            CmpOrdering::Less => false,
+            CmpOrdering::Equal => !(lower.inclusive && inclusive),
+            CmpOrdering::Greater => true,
+        },
+    }
+}
+
+// std::cmp::Ordering could be used but the
+// equals case(and then taking into account the is_inclusive case)
+// could very easily be a source of confusion and at that
+// point stops being analogical to it, so this is used instead
+#[derive(PartialEq, Eq)]
+pub enum Ordering {
+    ContainsFromLower,
+    ContainsFromUpper,
+    Equal,
+}
+
+fn contains_from_lower<'c, 'd, T, R>(a: T, b: R) -> Ordering
 where
     T: Into<(&'c Version, bool)>,
     R: Into<(&'d Version, bool)>,
 {
     let a = a.into();
     let b = b.into();
-    match a.0.cmp_precedence(&b.0) {
+    match a.0.cmp_precedence(b.0) {
         std::cmp::Ordering::Less => Ordering::ContainsFromLower,
         std::cmp::Ordering::Equal => {
             if !b.1 && a.1 {
-                Ordering::ContainsFromUpper
-            } else if b.1 && !a.1 {
                 Ordering::ContainsFromLower
+            } else if b.1 && !a.1 {
+                Ordering::ContainsFromUpper
             } else {
                 Ordering::Equal
             }
@@ -319,12 +913,52 @@ where
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Bound {
-    pub upper: Range,
+    pub upper: BoundEnd,
     pub lower: Range,
 }
 
+impl Bound {
+    // Whether the version lies between both ends, by semver precedence
+    pub fn contains(&self, version: &Version) -> bool {
+        let above_lower = match version.cmp_precedence(&self.lower.version) {
+            CmpOrdering::Less => false,
+            CmpOrdering::Equal => self.lower.inclusive,
+            CmpOrdering::Greater => true,
+        };
+
+        above_lower && self.upper.admits(version)
+    }
+
+    // The versions both bounds take in, failing if there are none. Each end is taken from
+    // `other` only when it is strictly narrower, so an end both share is kept from `self`,
+    // which lets callers tell which bound set each end of the result
+    pub fn intersect(&self, other: &Bound) -> Result<Bound> {
+        let lower =
+            if contains_from_lower(&self.lower, &other.lower).eq(&Ordering::ContainsFromLower) {
+                other.lower.clone()
+            } else {
+                self.lower.clone()
+            };
+
+        let upper = if other.upper.is_below(&self.upper) {
+            other.upper.clone()
+        } else {
+            self.upper.clone()
+        };
+
+        if is_empty(&lower, &upper) {
+            return Err(ConstError::DisjointBoundsError {
+                first: VersionReq::from(self).to_string(),
+                second: VersionReq::from(other).to_string(),
+            });
+        }
+
+        Ok(Bound { lower, upper })
+    }
+}
+
 impl TryFrom<&VersionReq> for Bound {
     type Error = ConstError;
 
@@ -354,50 +988,10 @@ impl TryFrom<&VersionReq> for Bound {
             crate_version: String::default(),
         };
 
-        let Bound { upper, lower } = Bound::try_from(first)?;
-        let (lower, upper) = (
-            (lower.version, lower.inclusive),
-            (upper.version, upper.inclusive),
-        );
+        let first = Bound::try_from(first)?;
 
-        let bound = comparators.try_fold((lower, upper), |acc, next| {
-            Bound::try_from(next).and_then(|next| {
-                let (lower, upper) = (
-                    if contains_from_lower((&acc.0 .0, acc.0 .1), &next.lower)
-                        .eq(&Ordering::ContainsFromLower)
-                    {
-                        (next.lower.version, next.lower.inclusive)
-                    } else {
-                        (acc.0 .0, acc.0 .1)
-                    },
-                    if contains_from_upper((&acc.1 .0, acc.1 .1), &next.upper)
-                        .eq(&Ordering::ContainsFromUpper)
-                    {
-                        (next.upper.version, next.upper.inclusive)
-                    } else {
-                        (acc.1 .0, acc.1 .1)
-                    },
-                );
-
-                if contains_from_upper((&lower.0, lower.1), (&upper.0, upper.1))
-                    .eq(&Ordering::ContainsFromUpper)
-                {
-                    Err(err_closure())
-                } else {
-                    Ok((lower, upper))
-                }
-            })
-        })?;
-
-        Ok(Bound {
-            lower: Range {
-                version: bound.0 .0,
-                inclusive: bound.0 .1,
-            },
-            upper: Range {
-                version: bound.1 .0,
-                inclusive: bound.1 .1,
-            },
+        comparators.try_fold(first, |acc, next| {
+            Bound::try_from(next).and_then(|next| acc.intersect(&next).map_err(|_| err_closure()))
         })
     }
 }
@@ -416,209 +1010,745 @@ impl From<&Bound> for VersionReq {
             pre: bound.lower.version.pre.clone(),
         };
 
-        let upper_comparator = Comparator {
-            op: if bound.lower.inclusive {
-                Op::LessEq
+        let mut comparators = vec![lower_comparator];
+
+        // An open upper end is left as a single sided requirement
+        if let Some((version, inclusive)) = bound.upper.as_range() {
+            comparators.push(Comparator {
+                op: if inclusive { Op::LessEq } else { Op::Less },
+                major: version.major,
+                minor: Some(version.minor),
+                patch: Some(version.patch),
+                pre: version.pre.clone(),
+            });
+        }
+
+        VersionReq { comparators }
+    }
+}
+
+// The versions taken in by any of several bounds, kept in order and apart from each
+// other, for when a requirement leaves out versions inside the one interval it spans
+#[derive(Clone, PartialEq, Eq)]
+pub struct BoundSet {
+    bounds: Vec<Bound>,
+}
+
+impl BoundSet {
+    pub fn bounds(&self) -> &[Bound] {
+        &self.bounds
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    pub fn contains(&self, version: &Version) -> bool {
+        self.bounds.iter().any(|bound| bound.contains(version))
+    }
+
+    // The versions of this set that `other` doesn't take in, each bound is split into
+    // what lies below `other` and what lies above it
+    pub fn subtract(&self, other: &Bound) -> BoundSet {
+        let below = Bound {
+            lower: Range {
+                version: lowest_prerelease(&Version::new(0, 0, 0)),
+                inclusive: true,
+            },
+            upper: if other.lower.inclusive {
+                BoundEnd::Exclusive(other.lower.version.clone())
             } else {
-                Op::Less
+                BoundEnd::Inclusive(other.lower.version.clone())
             },
-            major: bound.upper.version.major,
-            minor: Some(bound.upper.version.minor),
-            patch: Some(bound.upper.version.patch),
-            pre: bound.upper.version.pre.clone(),
         };
 
-        VersionReq {
-            comparators: vec![lower_comparator, upper_comparator],
+        let above = other.upper.as_range().map(|(version, inclusive)| Bound {
+            lower: Range {
+                version: version.clone(),
+                inclusive: !inclusive,
+            },
+            upper: BoundEnd::Unbounded,
+        });
+
+        let bounds = self
+            .bounds
+            .iter()
+            .flat_map(|bound| {
+                [
+                    bound.intersect(&below).ok(),
+                    above.as_ref().and_then(|above| bound.intersect(above).ok()),
+                ]
+            })
+            .flatten()
+            .collect();
+
+        BoundSet { bounds }
+    }
+}
+
+impl From<Bound> for BoundSet {
+    fn from(bound: Bound) -> Self {
+        BoundSet {
+            bounds: vec![bound],
         }
     }
 }
 
-impl TryFrom<&Comparator> for Bound {
-    type Error = ConstError;
-    fn try_from(comparator: &Comparator) -> Result<Self> {
-        match comparator.op {
-            Op::Caret => Ok(Bound {
-                lower: Range {
-                    version: Version {
-                        major: comparator.major,
-                        minor: comparator.minor.unwrap_or(0),
-                        patch: comparator.patch.unwrap_or(0),
-                        pre: comparator.pre.clone(),
-                        build: BuildMetadata::EMPTY,
-                    },
-                    inclusive: true,
-                },
-                upper: Range {
-                    version: Version {
-                        major: comparator.major.add(1),
-                        minor: 0,
-                        patch: 0,
-                        pre: Prerelease::EMPTY,
-                        build: BuildMetadata::EMPTY,
-                    },
-                    inclusive: false,
-                },
-            }),
-            Op::Tilde => Ok(Bound {
+// Each bound as a requirement, joined the way npm writes alternatives
+impl std::fmt::Display for BoundSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let requirements = self
+            .bounds
+            .iter()
+            .map(|bound| VersionReq::from(bound).to_string())
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", requirements.join(" || "))
+    }
+}
+
+// Whether the requirement has a comparator naming a prerelease of the same major, minor
+// and patch as `version`, semver only takes in such prereleases
+fn names_prerelease(version_req: &VersionReq, version: &Version) -> bool {
+    version_req.comparators.iter().any(|comparator| {
+        !comparator.pre.is_empty()
+            && comparator.major.eq(&version.major)
+            && comparator.minor.eq(&Some(version.minor))
+            && comparator.patch.eq(&Some(version.patch))
+    })
+}
+
+// The versions within the bound that every dependent's requirement takes in. The bound
+// covers each requirement's comparators but a requirement also leaves out the prereleases
+// of every version it doesn't name one of, so for each version with prereleases among
+// `versions` that some requirement doesn't name, its prereleases are cut out of the bound
+pub fn exclude_unnamed_prereleases(
+    bound: &Bound,
+    dependents: &[((String, String), VersionReq)],
+    versions: &[ParsedVersion],
+) -> BoundSet {
+    let prereleased = versions
+        .iter()
+        .filter(|version| !version.num.pre.is_empty())
+        .map(|version| Version::new(version.num.major, version.num.minor, version.num.patch))
+        .collect::<BTreeSet<_>>();
+
+    prereleased
+        .into_iter()
+        .filter(|version| {
+            !dependents
+                .iter()
+                .all(|(_, version_req)| names_prerelease(version_req, version))
+        })
+        .fold(BoundSet::from(bound.clone()), |set, version| {
+            set.subtract(&Bound {
                 lower: Range {
-                    version: Version {
-                        major: comparator.major,
-                        minor: comparator.minor.unwrap_or(0),
-                        patch: comparator.patch.unwrap_or(0),
-                        pre: comparator.pre.clone(),
-                        build: BuildMetadata::EMPTY,
-                    },
+                    version: lowest_prerelease(&version),
                     inclusive: true,
                 },
-                upper: Range {
-                    version: Version {
-                        major: comparator.major,
-                        minor: comparator.minor.unwrap_or(0).add(1),
-                        patch: 0,
-                        pre: Prerelease::EMPTY,
-                        build: BuildMetadata::EMPTY,
-                    },
-                    inclusive: false,
-                },
-            }),
-            Op::Exact => Ok(Bound {
-                lower: Range {
-                    version: Version {
-                        major: comparator.major,
-                        minor: comparator.minor.unwrap_or(0),
-                        patch: comparator.patch.unwrap_or(0),
-                        pre: comparator.pre.clone(),
-                        build: BuildMetadata::EMPTY,
-                    },
-                    inclusive: true,
+                upper: BoundEnd::Exclusive(version),
+            })
+        })
+}
+
+// The first prerelease of a version, "0" comes before every other identifier
+fn lowest_prerelease(version: &Version) -> Version {
+    Version {
+        pre: Prerelease::new("0").unwrap(),
+        ..version.clone()
+    }
+}
+
+// The shortest requirement that takes in the same versions as the bound, an exact, caret
+// or tilde one where one does, otherwise the two sided one `VersionReq::from` gives
+pub fn simplest_requirement(bound: &Bound) -> VersionReq {
+    let Version {
+        major,
+        minor,
+        patch,
+        ..
+    } = bound.lower.version;
+
+    let candidates = [
+        (Op::Exact, Some(minor), Some(patch)),
+        (Op::Caret, None, None),
+        (Op::Caret, Some(minor), None),
+        (Op::Caret, Some(minor), Some(patch)),
+        (Op::Tilde, Some(minor), None),
+        (Op::Tilde, Some(minor), Some(patch)),
+    ];
+
+    for (op, minor, patch) in candidates {
+        let comparator = Comparator {
+            op,
+            major,
+            minor,
+            patch,
+            pre: bound.lower.version.pre.clone(),
+        };
+
+        if Bound::try_from(&comparator).is_ok_and(|candidate| candidate.eq(bound)) {
+            return VersionReq {
+                comparators: vec![comparator],
+            };
+        }
+    }
+
+    VersionReq::from(bound)
+}
+
+// The first version a caret requirement leaves out, the leftmost non-zero part of the
+// version given is the one that can't change
+fn get_caret_upper(comparator: &Comparator) -> Version {
+    let (major, minor, patch) = match (comparator.major, comparator.minor, comparator.patch) {
+        (0, Some(0), Some(patch)) => (0, 0, patch.add(1)),
+        (0, Some(minor), _) => (0, minor.add(1), 0),
+        (major, _, _) => (major.add(1), 0, 0),
+    };
+
+    Version {
+        major,
+        minor,
+        patch,
+        pre: Prerelease::EMPTY,
+        build: BuildMetadata::EMPTY,
+    }
+}
+
+// The end of a caret, tilde or wildcard requirement, at the first version it leaves out.
+// Its prereleases come before it, so with --pre they are left out too as cargo would
+fn exclusive_upper(version: Version, pre: bool) -> BoundEnd {
+    match pre {
+        true => BoundEnd::Exclusive(lowest_prerelease(&version)),
+        false => BoundEnd::Exclusive(version),
+    }
+}
+
+impl TryFrom<&Comparator> for Bound {
+    type Error = ConstError;
+    fn try_from(comparator: &Comparator) -> Result<Self> {
+        comparator_bound(comparator, get_config().pre)
+    }
+}
+
+fn comparator_bound(comparator: &Comparator, pre: bool) -> Result<Bound> {
+    match comparator.op {
+        Op::Caret => Ok(Bound {
+            lower: Range {
+                version: Version {
+                    major: comparator.major,
+                    minor: comparator.minor.unwrap_or(0),
+                    patch: comparator.patch.unwrap_or(0),
+                    pre: comparator.pre.clone(),
+                    build: BuildMetadata::EMPTY,
                 },
-                upper: Range {
-                    version: Version {
-                        major: comparator.major,
-                        minor: comparator.minor.unwrap_or(0),
-                        patch: comparator.patch.unwrap_or(0),
-                        pre: comparator.pre.clone(),
-                        build: BuildMetadata::EMPTY,
-                    },
-                    inclusive: true,
+                inclusive: true,
+            },
+            upper: exclusive_upper(get_caret_upper(comparator), pre),
+        }),
+        Op::Tilde => Ok(Bound {
+            lower: Range {
+                version: Version {
+                    major: comparator.major,
+                    minor: comparator.minor.unwrap_or(0),
+                    patch: comparator.patch.unwrap_or(0),
+                    pre: comparator.pre.clone(),
+                    build: BuildMetadata::EMPTY,
                 },
-            }),
-            Op::Greater => Ok(Bound {
-                lower: Range {
-                    version: Version {
-                        major: comparator.major,
-                        minor: comparator.minor.unwrap_or(0),
-                        patch: comparator.patch.unwrap_or(0),
-                        pre: comparator.pre.clone(),
-                        build: BuildMetadata::EMPTY,
-                    },
-                    inclusive: false,
+                inclusive: true,
+            },
+            upper: exclusive_upper(
+                Version {
+                    major: comparator.major,
+                    minor: comparator.minor.unwrap_or(0).add(1),
+                    patch: 0,
+                    pre: Prerelease::EMPTY,
+                    build: BuildMetadata::EMPTY,
                 },
-                upper: Range {
-                    version: Version {
-                        major: u64::MAX,
-                        minor: u64::MAX,
-                        patch: u64::MAX,
-                        pre: Prerelease::EMPTY,
-                        build: BuildMetadata::EMPTY,
-                    },
-                    inclusive: true,
+                pre,
+            ),
+        }),
+        Op::Exact => Ok(Bound {
+            lower: Range {
+                version: Version {
+                    major: comparator.major,
+                    minor: comparator.minor.unwrap_or(0),
+                    patch: comparator.patch.unwrap_or(0),
+                    pre: comparator.pre.clone(),
+                    build: BuildMetadata::EMPTY,
                 },
+                inclusive: true,
+            },
+            upper: BoundEnd::Inclusive(Version {
+                major: comparator.major,
+                minor: comparator.minor.unwrap_or(0),
+                patch: comparator.patch.unwrap_or(0),
+                pre: comparator.pre.clone(),
+                build: BuildMetadata::EMPTY,
             }),
-            Op::GreaterEq => Ok(Bound {
-                lower: Range {
-                    version: Version {
-                        major: comparator.major,
-                        minor: comparator.minor.unwrap_or(0),
-                        patch: comparator.patch.unwrap_or(0),
-                        pre: comparator.pre.clone(),
-                        build: BuildMetadata::EMPTY,
-                    },
-                    inclusive: true,
-                },
-                upper: Range {
-                    version: Version {
-                        major: u64::MAX,
-                        minor: u64::MAX,
-                        patch: u64::MAX,
-                        pre: Prerelease::EMPTY,
-                        build: BuildMetadata::EMPTY,
-                    },
-                    inclusive: true,
+        }),
+        Op::Greater => Ok(Bound {
+            lower: Range {
+                version: Version {
+                    major: comparator.major,
+                    minor: comparator.minor.unwrap_or(0),
+                    patch: comparator.patch.unwrap_or(0),
+                    pre: comparator.pre.clone(),
+                    build: BuildMetadata::EMPTY,
                 },
-            }),
-            Op::Less => Ok(Bound {
-                lower: Range {
-                    version: Version {
-                        major: 0,
-                        minor: 0,
-                        patch: 0,
-                        pre: Prerelease::EMPTY,
-                        build: BuildMetadata::EMPTY,
-                    },
-                    inclusive: true,
+                inclusive: false,
+            },
+            upper: BoundEnd::Unbounded,
+        }),
+        Op::GreaterEq => Ok(Bound {
+            lower: Range {
+                version: Version {
+                    major: comparator.major,
+                    minor: comparator.minor.unwrap_or(0),
+                    patch: comparator.patch.unwrap_or(0),
+                    pre: comparator.pre.clone(),
+                    build: BuildMetadata::EMPTY,
                 },
-                upper: Range {
-                    version: Version {
-                        major: comparator.major,
-                        minor: comparator.minor.unwrap_or(0),
-                        patch: comparator.patch.unwrap_or(0),
-                        pre: comparator.pre.clone(),
-                        build: BuildMetadata::EMPTY,
-                    },
-                    inclusive: false,
+                inclusive: true,
+            },
+            upper: BoundEnd::Unbounded,
+        }),
+        Op::Less => Ok(Bound {
+            lower: Range {
+                version: Version {
+                    major: 0,
+                    minor: 0,
+                    patch: 0,
+                    pre: Prerelease::EMPTY,
+                    build: BuildMetadata::EMPTY,
                 },
+                inclusive: true,
+            },
+            upper: BoundEnd::Exclusive(Version {
+                major: comparator.major,
+                minor: comparator.minor.unwrap_or(0),
+                patch: comparator.patch.unwrap_or(0),
+                pre: comparator.pre.clone(),
+                build: BuildMetadata::EMPTY,
             }),
-            Op::LessEq => Ok(Bound {
-                lower: Range {
-                    version: Version {
-                        major: 0,
-                        minor: 0,
-                        patch: 0,
-                        pre: Prerelease::EMPTY,
-                        build: BuildMetadata::EMPTY,
-                    },
-                    inclusive: true,
-                },
-                upper: Range {
-                    version: Version {
-                        major: comparator.major,
-                        minor: comparator.minor.unwrap_or(0),
-                        patch: comparator.patch.unwrap_or(0),
-                        pre: comparator.pre.clone(),
-                        build: BuildMetadata::EMPTY,
-                    },
-                    inclusive: true,
+        }),
+        Op::LessEq => Ok(Bound {
+            lower: Range {
+                version: Version {
+                    major: 0,
+                    minor: 0,
+                    patch: 0,
+                    pre: Prerelease::EMPTY,
+                    build: BuildMetadata::EMPTY,
                 },
+                inclusive: true,
+            },
+            upper: BoundEnd::Inclusive(Version {
+                major: comparator.major,
+                minor: comparator.minor.unwrap_or(0),
+                patch: comparator.patch.unwrap_or(0),
+                pre: comparator.pre.clone(),
+                build: BuildMetadata::EMPTY,
             }),
-            Op::Wildcard => Ok(Bound {
-                lower: Range {
-                    version: Version {
-                        major: 0,
-                        minor: 0,
-                        patch: 0,
-                        pre: Prerelease::EMPTY,
-                        build: BuildMetadata::EMPTY,
-                    },
-                    inclusive: true,
+        }),
+        // A bare * has no comparators, so a wildcard always has its major and
+        // matches like a tilde on the parts before the *
+        Op::Wildcard => Ok(Bound {
+            lower: Range {
+                version: Version {
+                    major: comparator.major,
+                    minor: comparator.minor.unwrap_or(0),
+                    patch: 0,
+                    pre: Prerelease::EMPTY,
+                    build: BuildMetadata::EMPTY,
                 },
-                upper: Range {
-                    version: Version {
-                        major: u64::MAX,
-                        minor: u64::MAX,
-                        patch: u64::MAX,
-                        pre: Prerelease::EMPTY,
-                        build: BuildMetadata::EMPTY,
-                    },
-                    inclusive: true,
+                inclusive: true,
+            },
+            upper: exclusive_upper(
+                match comparator.minor {
+                    Some(minor) => Version::new(comparator.major, minor.add(1), 0),
+                    None => Version::new(comparator.major.add(1), 0, 0),
                 },
-            }),
-            _ => Err(ConstError::VersionError(
-                comparator.clone(),
-                String::default(),
-                UNSUPPORTED_SEMVER_OPERATOR,
-            )),
+                pre,
+            ),
+        }),
+        _ => Err(ConstError::VersionError(
+            comparator.clone(),
+            String::default(),
+            UNSUPPORTED_SEMVER_OPERATOR,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dependent(name: &str, version: &str) -> (String, String) {
+        (name.to_string(), version.to_string())
+    }
+
+    #[test]
+    fn dependents_sort_by_name_then_semver() {
+        let mut dependents = vec![
+            dependent("b", "0.10.0"),
+            dependent("a", "0.10.0"),
+            dependent("b", "0.9.0"),
+            dependent("a", "0.9.1"),
+            dependent("a", "0.9.0"),
+        ];
+
+        dependents.sort_by(cmp_dependents);
+
+        assert_eq!(
+            dependents,
+            vec![
+                dependent("a", "0.9.0"),
+                dependent("a", "0.9.1"),
+                dependent("a", "0.10.0"),
+                dependent("b", "0.9.0"),
+                dependent("b", "0.10.0"),
+            ]
+        );
+    }
+
+    #[test]
+    fn dependents_with_unparsable_versions_sort_as_text() {
+        assert_eq!(
+            cmp_dependents(&dependent("a", "latest"), &dependent("a", "0.10.0")),
+            CmpOrdering::Greater
+        );
+    }
+
+    fn bound(requirement: &str) -> Bound {
+        Bound::try_from(&VersionReq::parse(requirement).unwrap()).unwrap()
+    }
+
+    fn requirement(name: &str, requirement: &str) -> ((String, String), VersionReq) {
+        (
+            dependent(name, "1.0.0"),
+            VersionReq::parse(requirement).unwrap(),
+        )
+    }
+
+    fn published(versions: &[&str]) -> Vec<ParsedVersion> {
+        versions
+            .iter()
+            .map(|version| ParsedVersion {
+                yanked: false,
+                num: Version::parse(version).unwrap(),
+                rust_version: None,
+                created_at: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn subtracting_from_the_middle_splits_the_bound() {
+        let set = BoundSet::from(bound(">=1.0.0, <2.0.0")).subtract(&bound(">=1.2.0, <1.3.0"));
+
+        assert_eq!(set.bounds().len(), 2);
+        assert_eq!(set.to_string(), ">=1.0.0, <1.2.0 || >=1.3.0, <2.0.0");
+        assert!(set.contains(&Version::parse("1.1.9").unwrap()));
+        assert!(!set.contains(&Version::parse("1.2.5").unwrap()));
+        assert!(set.contains(&Version::parse("1.3.0").unwrap()));
+    }
+
+    #[test]
+    fn subtracting_keeps_the_ends_it_doesnt_cover() {
+        let set = BoundSet::from(bound(">=1.0.0, <2.0.0")).subtract(&bound(">1.0.0, <=1.5.0"));
+
+        assert_eq!(set.to_string(), ">=1.0.0, <=1.0.0 || >1.5.0, <2.0.0");
+        assert!(set.contains(&Version::parse("1.0.0").unwrap()));
+        assert!(!set.contains(&Version::parse("1.5.0").unwrap()));
+    }
+
+    #[test]
+    fn subtracting_everything_leaves_nothing() {
+        let set = BoundSet::from(bound(">=1.2.0, <1.3.0")).subtract(&bound(">=1.0.0, <2.0.0"));
+
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn subtracting_outside_the_bound_changes_nothing() {
+        let set = BoundSet::from(bound("^1.2")).subtract(&bound(">=3.0.0"));
+
+        assert!(set == BoundSet::from(bound("^1.2")));
+    }
+
+    #[test]
+    fn prereleases_no_dependent_names_are_cut_out() {
+        let dependents = [requirement("a", "^1.0"), requirement("b", ">=1.1.0")];
+        let versions = published(&["1.0.0", "1.1.0", "1.2.0-beta.1", "1.2.0", "2.0.0-rc.1"]);
+
+        let set = exclude_unnamed_prereleases(&bound(">=1.1.0, <2.0.0"), &dependents, &versions);
+
+        assert_eq!(set.to_string(), ">=1.1.0, <1.2.0-0 || >=1.2.0, <2.0.0-0");
+
+        for version in &versions {
+            assert_eq!(
+                set.contains(&version.num),
+                dependents
+                    .iter()
+                    .all(|(_, version_req)| version_req.matches(&version.num)),
+                "{}",
+                version.num
+            );
+        }
+    }
+
+    #[test]
+    fn prereleases_are_kept_when_every_dependent_names_them() {
+        let dependents = [
+            requirement("a", ">=1.2.0-alpha"),
+            requirement("b", "^1.2.0-beta"),
+        ];
+        let versions = published(&["1.2.0-alpha", "1.2.0-beta.1", "1.2.0", "1.3.0-rc.1"]);
+
+        let set =
+            exclude_unnamed_prereleases(&bound(">=1.2.0-beta, <2.0.0"), &dependents, &versions);
+
+        assert!(set.contains(&Version::parse("1.2.0-beta.1").unwrap()));
+        assert!(!set.contains(&Version::parse("1.3.0-rc.1").unwrap()));
+        assert_eq!(set.to_string(), ">=1.2.0-beta, <1.3.0-0 || >=1.3.0, <2.0.0");
+    }
+
+    #[test]
+    fn prereleases_named_by_only_some_dependents_are_cut_out() {
+        let dependents = [requirement("a", "=1.2.0-beta.1"), requirement("b", "^1")];
+        let versions = published(&["1.2.0-beta.1"]);
+
+        let set = exclude_unnamed_prereleases(&bound("=1.2.0-beta.1"), &dependents, &versions);
+
+        assert!(set.is_empty());
+    }
+
+    fn ends(bound: &Bound) -> ((Version, bool), Option<(Version, bool)>) {
+        (
+            (bound.lower.version.clone(), bound.lower.inclusive),
+            bound
+                .upper
+                .as_range()
+                .map(|(version, inclusive)| (version.clone(), inclusive)),
+        )
+    }
+
+    // --print-bound-only prints the bound as a requirement, which has to read back as the
+    // same bound with each end keeping its own operator
+    #[test]
+    fn bounds_round_trip_through_requirements() {
+        let cases = [
+            ("=1.2.3", vec![Op::GreaterEq, Op::LessEq]),
+            (">1.2.3", vec![Op::Greater]),
+            (">=1.2.3", vec![Op::GreaterEq]),
+            ("<1.2.3", vec![Op::GreaterEq, Op::Less]),
+            ("<=1.2.3", vec![Op::GreaterEq, Op::LessEq]),
+            ("~1.2.3", vec![Op::GreaterEq, Op::Less]),
+            ("^1.2.3", vec![Op::GreaterEq, Op::Less]),
+            ("1.2.*", vec![Op::GreaterEq, Op::Less]),
+            (">1.2.3, <=1.4.0", vec![Op::Greater, Op::LessEq]),
+        ];
+
+        for (requirement, ops) in cases {
+            let bound = bound(requirement);
+            let printed = VersionReq::from(&bound);
+
+            assert_eq!(
+                printed
+                    .comparators
+                    .iter()
+                    .map(|comparator| comparator.op)
+                    .collect::<Vec<_>>(),
+                ops,
+                "{}",
+                requirement
+            );
+
+            let read_back = Bound::try_from(&VersionReq::parse(&printed.to_string()).unwrap());
+            assert_eq!(ends(&read_back.unwrap()), ends(&bound), "{}", requirement);
+        }
+    }
+
+    // Cargo only lets the parts after the leftmost non-zero one change under a caret
+    #[test]
+    fn caret_upper_follows_the_leftmost_non_zero_part() {
+        let cases = [
+            ("^1.2.3", "2.0.0"),
+            ("^1.2", "2.0.0"),
+            ("^0.4", "0.5.0"),
+            ("^0.4.1", "0.5.0"),
+            ("^0.0.3", "0.0.4"),
+            ("^0.0", "0.1.0"),
+            ("^0", "1.0.0"),
+        ];
+
+        for (requirement, upper) in cases {
+            let bound = bound(requirement);
+
+            assert!(
+                matches!(&bound.upper, BoundEnd::Exclusive(end) if end.to_string() == upper),
+                "{}",
+                requirement
+            );
+        }
+    }
+
+    // Without --pre the prereleases of the next version are still inside, as they always
+    // were, with it they are left out like cargo does
+    #[test]
+    fn pre_leaves_the_next_versions_prereleases_out() {
+        let cases = [
+            ("^1.2", "2.0.0-alpha.1", "<2.0.0-0"),
+            ("~1.9", "1.10.0-alpha.1", "<1.10.0-0"),
+            ("1.*", "2.0.0-alpha.1", "<2.0.0-0"),
+            ("1.2.*", "1.3.0-rc.1", "<1.3.0-0"),
+        ];
+
+        for (requirement, prerelease, upper) in cases {
+            let comparator = Comparator::parse(requirement).unwrap();
+            let prerelease = Version::parse(prerelease).unwrap();
+
+            let without = comparator_bound(&comparator, false).unwrap();
+            let with = comparator_bound(&comparator, true).unwrap();
+
+            assert!(without.contains(&prerelease), "{}", requirement);
+            assert!(!with.contains(&prerelease), "{}", requirement);
+            assert_eq!(
+                VersionReq::from(&with).to_string().rsplit(", ").next(),
+                Some(upper),
+                "{}",
+                requirement
+            );
+        }
+    }
+
+    // Versions that only differ in build metadata have the same precedence, so they have
+    // to end up together on the same side of either end
+    #[test]
+    fn build_metadata_stays_on_one_side_of_the_bound() {
+        let mut versions = published(&[
+            "2.0.0+z", "1.1.0+x", "1.0.0+b", "1.1.0", "1.0.0", "1.0.0+a", "0.9.0+a",
+        ]);
+        versions.sort();
+
+        assert_eq!(
+            versions
+                .iter()
+                .map(|version| version.num.to_string())
+                .collect::<Vec<_>>(),
+            ["0.9.0+a", "1.0.0", "1.0.0+a", "1.0.0+b", "1.1.0", "1.1.0+x", "2.0.0+z"]
+        );
+
+        assert_eq!(find_range(&versions, &bound(">=1.0.0, <2.0.0")), (1, 6));
+        assert_eq!(find_range(&versions, &bound(">1.0.0, <=1.1.0")), (4, 6));
+        assert_eq!(find_range(&versions, &bound("=1.0.0")), (1, 4));
+        assert_eq!(find_range(&versions, &bound("<1.0.0")), (0, 1));
+        assert_eq!(find_range(&versions, &bound(">=2.0.0")), (6, 7));
+    }
+
+    #[test]
+    fn wildcards_only_free_the_parts_they_replace() {
+        let cases = [
+            ("1.*", ">=1.0.0, <2.0.0"),
+            ("1.x", ">=1.0.0, <2.0.0"),
+            ("0.*", ">=0.0.0, <1.0.0"),
+            ("1.2.*", ">=1.2.0, <1.3.0"),
+            ("1.2.X", ">=1.2.0, <1.3.0"),
+            ("0.0.*", ">=0.0.0, <0.1.0"),
+        ];
+
+        for (wildcard, expected) in cases {
+            let comparator = Comparator::parse(wildcard).unwrap();
+            assert_eq!(comparator.op, Op::Wildcard, "{}", wildcard);
+
+            let bound = Bound::try_from(&comparator).unwrap();
+            assert_eq!(
+                VersionReq::from(&bound).to_string(),
+                expected,
+                "{}",
+                wildcard
+            );
+        }
+
+        let versions = published(&["1.1.9", "1.2.0", "1.2.9", "1.3.0"]);
+        assert_eq!(find_range(&versions, &bound("1.2.*")), (1, 3));
+    }
+
+    // A bare * has no comparators, so it is never turned into a bound
+    #[test]
+    fn a_bare_wildcard_has_no_bound() {
+        assert!(VersionReq::parse("*").unwrap().comparators.is_empty());
+        assert!(matches!(
+            Bound::try_from(&VersionReq::STAR),
+            Err(ConstError::EmptyVersionReqError { .. })
+        ));
+    }
+
+    fn intersection(first: &str, second: &str) -> Result<String> {
+        let result = bound(first).intersect(&bound(second))?;
+
+        // The versions taken in don't depend on the order the bounds are given in
+        let swapped = bound(second).intersect(&bound(first)).unwrap();
+        assert!(result == swapped, "{} and {}", first, second);
+
+        Ok(VersionReq::from(&result).to_string())
+    }
+
+    #[test]
+    fn intersecting_overlapping_bounds_keeps_the_narrower_ends() {
+        assert_eq!(
+            intersection(">=1.0.0, <2.0.0", ">=1.5.0, <3.0.0").unwrap(),
+            ">=1.5.0, <2.0.0"
+        );
+        assert_eq!(intersection("^1.2", ">=1.0.0").unwrap(), ">=1.2.0, <2.0.0");
+        assert_eq!(intersection(">=1.0.0", ">=0.5.0").unwrap(), ">=1.0.0");
+    }
+
+    #[test]
+    fn intersecting_at_the_same_version_keeps_the_exclusive_end() {
+        assert_eq!(intersection(">=1.0.0", ">1.0.0").unwrap(), ">1.0.0");
+        assert_eq!(
+            intersection(">=1.0.0, <=2.0.0", ">=1.0.0, <2.0.0").unwrap(),
+            ">=1.0.0, <2.0.0"
+        );
+    }
+
+    #[test]
+    fn bounds_touching_at_an_inclusive_end_share_that_version() {
+        assert_eq!(
+            intersection(">=1.0.0, <=1.5.0", ">=1.5.0").unwrap(),
+            ">=1.5.0, <=1.5.0"
+        );
+    }
+
+    #[test]
+    fn bounds_touching_at_an_exclusive_end_are_disjoint() {
+        for (first, second) in [
+            (">=1.0.0, <1.5.0", ">=1.5.0"),
+            (">=1.0.0, <=1.5.0", ">1.5.0"),
+            (">=1.0.0, <1.5.0", ">1.5.0"),
+        ] {
+            assert!(
+                matches!(
+                    bound(first).intersect(&bound(second)),
+                    Err(ConstError::DisjointBoundsError { .. })
+                ),
+                "{} and {}",
+                first,
+                second
+            );
+        }
+    }
+
+    #[test]
+    fn disjoint_bounds_name_both_requirements() {
+        match bound("^1").intersect(&bound("^2")) {
+            Err(ConstError::DisjointBoundsError { first, second }) => {
+                assert_eq!(first, ">=1.0.0, <2.0.0");
+                assert_eq!(second, ">=2.0.0, <3.0.0");
+            }
+            _ => panic!("^1 and ^2 should be disjoint"),
         }
     }
 }
@@ -0,0 +1,123 @@
+use clap::Parser;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    error::{ConstError, Result},
+    provider::get_data_location,
+};
+
+/// Manage the cache of responses from crates.io
+#[derive(Parser)]
+pub struct Cache {
+    #[clap(subcommand)]
+    action: CacheAction,
+}
+
+#[derive(Parser)]
+enum CacheAction {
+    Clear(Clear),
+    /// Print where the cache is, whether it exists and its size
+    Path,
+}
+
+/// Remove everything cached by this version of cargo-const
+#[derive(Parser)]
+struct Clear {
+    /// List the files that would be removed without removing them
+    #[clap(long)]
+    dry_run: bool,
+}
+
+impl Cache {
+    pub fn run(self) -> Result<()> {
+        match self.action {
+            CacheAction::Clear(clear) => clear.run(),
+            CacheAction::Path => print_path(),
+        }
+    }
+}
+
+fn print_path() -> Result<()> {
+    let data_dir = get_data_location().ok_or(ConstError::DataDirectoryError)?;
+
+    println!("{}", data_dir.display());
+
+    if !data_dir.exists() {
+        println!("Does not exist yet, it is created on the first fetch from crates.io");
+        return Ok(());
+    }
+
+    let size = list_files(&data_dir)
+        .and_then(|files| {
+            files
+                .iter()
+                .map(|file| std::fs::metadata(file).map(|metadata| metadata.len()))
+                .sum::<std::io::Result<u64>>()
+        })
+        .map_err(|error| ConstError::OpenFileError {
+            path: data_dir.to_string_lossy().to_string(),
+            error,
+        })?;
+
+    println!("Exists, {} bytes", size);
+
+    Ok(())
+}
+
+impl Clear {
+    fn run(self) -> Result<()> {
+        let data_dir = get_data_location().ok_or(ConstError::DataDirectoryError)?;
+
+        if !data_dir.exists() {
+            println!("Nothing to clear, {} does not exist", data_dir.display());
+            return Ok(());
+        }
+
+        let files = list_files(&data_dir).map_err(|error| ConstError::ClearCacheError {
+            path: data_dir.to_string_lossy().to_string(),
+            error,
+        })?;
+
+        if self.dry_run {
+            for file in &files {
+                println!("{}", file.display());
+            }
+
+            println!(
+                "Would remove {} files from {}",
+                files.len(),
+                data_dir.display()
+            );
+
+            return Ok(());
+        }
+
+        std::fs::remove_dir_all(&data_dir).map_err(|error| ConstError::ClearCacheError {
+            path: data_dir.to_string_lossy().to_string(),
+            error,
+        })?;
+
+        println!("Removed {} files from {}", files.len(), data_dir.display());
+
+        Ok(())
+    }
+}
+
+// Every file under `dir`, in order of path
+fn list_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            files.extend(list_files(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
@@ -1,15 +1,48 @@
-use cargo_lock::Lockfile;
+use cargo_lock::{Lockfile, ResolveVersion};
 use clap::Parser;
-use std::str::FromStr;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Instant,
+};
 
 use crate::{
-    bound::find_packed_bound,
+    bound::{
+        check_in_lockfile, collect_constraints, exclude_unnamed_prereleases, filter_locked_to,
+        find_dependents, find_downgrade, find_locked_versions, find_reachable, match_versions,
+        merge_constraints, merge_dependents, merge_dependents_cached, raise_lower_bound,
+        simplest_requirement, Bound, BoundSet, DependentConstraint, DependentIndex, MergedBound,
+        PackedBound, ResolutionKey,
+    },
     error::{ConstError, Result},
-    provider::Provider,
-    utils::{get_rust_version, print_header_and_items},
+    explain::{explain_merge, explain_resolution},
+    get_config,
+    lockfile::{get_lockfile_rust_versions, parse_lockfile_rust_versions},
+    manifest::{
+        get_dependency_requirements, get_dependency_tables, get_manifest_path,
+        get_manifest_rust_version, get_package_id, get_toolchain_rust_version,
+        get_workspace_members, pin_dependency, WorkspaceMember,
+    },
+    metadata::{find_dependents_from_metadata, FeatureSelection},
+    provider::{ParsedDependency, ParsedVersion, Provider},
+    render::{
+        JsonRenderer, JunitRenderer, Output, PlainRenderer, Renderer, TextRenderer, TomlRenderer,
+    },
+    report::{CompatReport, PinPreview, ReportBound},
+    utils::{
+        eprint_header_and_items, get_rust_version, get_rustc_version, is_colored, is_verbose,
+        print_header_and_items, print_warning, take_warnings, unified_diff, Verbosity,
+    },
 };
 
-#[derive(Debug)]
+// The --path that reads the lockfile from stdin
+const STDIN_PATH: &str = "-";
+
+#[derive(Debug, Clone)]
 pub enum Count {
     All,
     Count(usize),
@@ -20,107 +53,2141 @@ impl FromStr for Count {
     fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
         match value {
             "all" => Ok(Count::All),
-            value => {
-                let count = value
-                    .parse()
-                    .map_err(|_| ConstError::InvalidCountArgument {
-                        argument: value.to_string(),
-                    })?;
-                Ok(Count::Count(count))
-            }
+            // Zero would list nothing and look like nothing was found
+            value => match value.parse() {
+                Ok(0) | Err(_) => Err(ConstError::InvalidCountArgument {
+                    argument: value.to_string(),
+                }),
+                Ok(count) => Ok(Count::Count(count)),
+            },
+        }
+    }
+}
+
+impl Count {
+    // The most versions to list
+    pub fn limit(&self) -> usize {
+        match self {
+            Count::All => usize::MAX,
+            Count::Count(count) => *count,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Plain,
+    Junit,
+    Toml,
+}
+
+impl FromStr for Format {
+    type Err = ConstError;
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "plain" => Ok(Format::Plain),
+            "junit" => Ok(Format::Junit),
+            "toml" => Ok(Format::Toml),
+            value => Err(ConstError::InvalidFormatArgument {
+                argument: value.to_string(),
+            }),
+        }
+    }
+}
+
+// How far along a version is towards release, taken from the first identifier of its
+// prerelease, any identifier other than the ones below ranks lowest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stability {
+    Other,
+    Alpha,
+    Beta,
+    Rc,
+    Stable,
+}
+
+impl Stability {
+    fn of(version: &Version) -> Stability {
+        if version.pre.is_empty() {
+            return Stability::Stable;
+        }
+
+        let identifier = version
+            .pre
+            .split('.')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        if identifier.starts_with("alpha") {
+            Stability::Alpha
+        } else if identifier.starts_with("beta") {
+            Stability::Beta
+        } else if identifier.starts_with("rc") {
+            Stability::Rc
+        } else {
+            Stability::Other
+        }
+    }
+}
+
+impl FromStr for Stability {
+    type Err = ConstError;
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "alpha" => Ok(Stability::Alpha),
+            "beta" => Ok(Stability::Beta),
+            "rc" => Ok(Stability::Rc),
+            "stable" => Ok(Stability::Stable),
+            value => Err(ConstError::InvalidStabilityArgument {
+                argument: value.to_string(),
+            }),
+        }
+    }
+}
+
+// The order the versions are listed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sort {
+    // Newest first
+    Desc,
+    Asc,
+    // By the rust version needed, lowest first, those that don't say last
+    Msrv,
+}
+
+impl FromStr for Sort {
+    type Err = ConstError;
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "desc" => Ok(Sort::Desc),
+            "asc" => Ok(Sort::Asc),
+            "msrv" => Ok(Sort::Msrv),
+            value => Err(ConstError::InvalidSortArgument {
+                argument: value.to_string(),
+            }),
+        }
+    }
+}
+
+// Which kinds of dependency on a crate take part in the bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum DependencyKind {
+    Normal,
+    Build,
+    Dev,
+    All,
+}
+
+impl DependencyKind {
+    // Whether a dependency of `kind`, as crates.io names it, takes part
+    pub fn includes(&self, kind: &str) -> bool {
+        match self {
+            DependencyKind::Normal => kind.eq("normal"),
+            DependencyKind::Build => kind.eq("build"),
+            DependencyKind::Dev => kind.eq("dev"),
+            DependencyKind::All => true,
+        }
+    }
+}
+
+impl FromStr for DependencyKind {
+    type Err = ConstError;
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "normal" => Ok(DependencyKind::Normal),
+            "build" => Ok(DependencyKind::Build),
+            "dev" => Ok(DependencyKind::Dev),
+            "all" => Ok(DependencyKind::All),
+            value => Err(ConstError::InvalidKindArgument {
+                argument: value.to_string(),
+            }),
+        }
+    }
+}
+
+// Where to take the max rust version from when --max-version isn't given
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RustVersionSource {
+    // The first found of the others, in the order below
+    Auto,
+    Toolchain,
+    Manifest,
+    Rustc,
+}
+
+impl FromStr for RustVersionSource {
+    type Err = ConstError;
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "auto" => Ok(RustVersionSource::Auto),
+            "toolchain" => Ok(RustVersionSource::Toolchain),
+            "manifest" => Ok(RustVersionSource::Manifest),
+            "rustc" => Ok(RustVersionSource::Rustc),
+            value => Err(ConstError::InvalidRustVersionSourceArgument {
+                argument: value.to_string(),
+            }),
         }
     }
 }
 
+// An extra output written to a file as `FORMAT=PATH`, alongside the one printed
+#[derive(Debug)]
+pub struct Emit {
+    format: Format,
+    path: PathBuf,
+}
+
+impl FromStr for Emit {
+    type Err = ConstError;
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let error = || ConstError::InvalidEmitArgument {
+            argument: value.to_string(),
+        };
+
+        let (format, path) = value.split_once('=').ok_or_else(error)?;
+
+        let format = match format.parse() {
+            Ok(Format::Junit) | Err(_) => return Err(error()),
+            Ok(format) => format,
+        };
+
+        Ok(Emit {
+            format,
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+// The versions of a crate that passed every filter, newest first, along with the
+// dependents whose requirements were used to find them
+struct Selection {
+    crate_name: String,
+    versions: Vec<ParsedVersion>,
+    dependents: Vec<((String, String), VersionReq)>,
+    // The oldest and newest versions within the bound before any filtering
+    range: (Version, Version),
+    // The bound merged from the requirements and the positions of the dependents that
+    // set its lower and upper ends
+    bound: Bound,
+    // The versions within the bound left after --pre, the whole bound
+    // without it
+    allowed: BoundSet,
+    lower_index: usize,
+    upper_index: usize,
+    // The locked version the dependents were grouped by, when there is more than one
+    locked: Option<Version>,
+}
+
 /// Find all versions of a crate compatible with the project's dependencies
 #[derive(Parser)]
 pub struct Compat {
     /// Whether or not to include yanked versions
     #[clap(short, long)]
     include_yanked: bool,
-    /// List out versions instead of using a range
-    #[clap(short, long, default_value = "5")]
-    count: Count,
-    /// Path to cargo.lock
+    /// List out versions instead of using a range [default: 5]
+    #[clap(short, long)]
+    count: Option<Count>,
+    /// Path to cargo.lock, or - to read it from stdin
     #[clap(short, long, default_value = "Cargo.lock")]
     path: String,
     /// Max rust version supported
     #[clap(short, long)]
     max_version: Option<String>,
-    /// Dependency to find minimum version of
-    dependency: String,
+    /// Where to find the max rust version when --max-version isn't given, one of
+    /// "toolchain" (rust-toolchain.toml), "manifest" (rust-version in Cargo.toml), "rustc"
+    /// or "auto" to use the first of them found
+    #[clap(long)]
+    rust_version_source: Option<RustVersionSource>,
+    /// When --max-version isn't given, use the rust this project builds with as the max
+    /// rust version, the one pinned in rust-toolchain.toml or else the installed rustc
+    #[clap(long, conflicts_with = "rust-version-source")]
+    auto_msrv: bool,
+    /// Prefer the rust-version recorded in the lockfile over the one from crates.io
+    #[clap(long)]
+    lockfile_msrv: bool,
+    /// Output format, one of "text", "json", "plain", "junit" or "toml"
+    #[clap(
+        short,
+        long,
+        default_value = "text",
+        conflicts_with_all = &["best", "as-requirement", "dependents-of-version"]
+    )]
+    format: Format,
+    /// Leave out the min rust version after each version in the text output
+    #[clap(long)]
+    output_versions_only: bool,
+    /// Include the dependents and their requirements in the json output
+    #[clap(long)]
+    json_include_requirements: bool,
+    /// Print the Cargo.toml change that would pin the newest compatible version without
+    /// writing it, failing if there is a change to make
+    #[clap(long)]
+    pin_dry_run: bool,
+    /// Reuse the bound merged on an earlier run if the dependents haven't changed
+    #[clap(long)]
+    cache_resolution: bool,
+    /// Show how each dependent narrows the range of compatible versions
+    #[clap(long)]
+    explain: bool,
+    /// When the dependents' requirements conflict, show the most dependents that agree on
+    /// a range and which of the others need a newer or older version to fit in it
+    #[clap(long)]
+    explain_unsatisfiable: bool,
+    /// Features the project is built with, only dependencies active under them are used
+    #[clap(long, use_value_delimiter = true)]
+    features: Vec<String>,
+    /// Build the project with all features when finding active dependencies
+    #[clap(long)]
+    all_features: bool,
+    /// Build the project without default features when finding active dependencies
+    #[clap(long)]
+    no_default_features: bool,
+    /// Also list the workspace members that use the crate, and whether they declare it
+    /// as a normal, dev or build dependency
+    #[clap(long)]
+    workspace: bool,
+    /// Only use the dependencies of this workspace member, its features are the ones
+    /// selected with --features
+    #[clap(long)]
+    package: Option<String>,
+    /// Print only the newest non-yanked version supported by the max rust version,
+    /// detected from rustc when --max-version isn't given
+    #[clap(long, conflicts_with_all = &["at-least", "min", "as-requirement", "pin-dry-run"])]
+    best: bool,
+    /// Only use versions from this one up and print the oldest of them, such as the
+    /// first version with a fix
+    #[clap(long, conflicts_with_all = &["min", "as-requirement", "pin-dry-run"])]
+    at_least: Option<Version>,
+    /// Only list compatible versions this requirement also accepts, such as "1" for the
+    /// 1.x versions
+    #[clap(long)]
+    matching: Option<VersionReq>,
+    /// Print only the oldest version that every dependent accepts and that passes the
+    /// yanked and max rust version filters, for minimal version testing
+    #[clap(long, conflicts_with_all = &["as-requirement", "pin-dry-run"])]
+    min: bool,
+    /// Print only a line for Cargo.toml with the requirement that takes in the range every
+    /// dependent accepts, in caret form where that is the same
+    #[clap(long, conflicts_with = "pin-dry-run")]
+    as_requirement: bool,
+    /// List the yanked versions within the bound when they are all there is, also done
+    /// when verbose
+    #[clap(long)]
+    explain_yanked: bool,
+    /// Leave out prereleases the way cargo does, those a dependent's requirement doesn't
+    /// name and those of the version a caret, tilde or wildcard requirement stops at
+    #[clap(long)]
+    pre: bool,
+    /// Leave out prereleases less stable than this, one of "alpha", "beta", "rc" or
+    /// "stable"
+    #[clap(long)]
+    min_stability: Option<Stability>,
+    /// Order to list the versions in, one of "desc", "asc" or "msrv", which is by the rust
+    /// version needed, lowest first, with versions that don't say last
+    #[clap(long, default_value = "desc")]
+    sort: Sort,
+    /// When no version satisfies every dependent, look for an older version of a
+    /// conflicting dependent that resolves it. Slower and heavier on crates.io, as the
+    /// dependencies of each older version tried are fetched
+    #[clap(long)]
+    allow_downgrade: bool,
+    /// Only take the requirements of this kind of dependency into account, one of
+    /// "normal", "build", "dev" or "all"
+    #[clap(long, default_value = "normal")]
+    kind: DependencyKind,
+    /// Resolve a crate taken from git against its versions on crates.io anyway
+    #[clap(long)]
+    treat_as_registry: bool,
+    /// List this many versions spread evenly from the newest to the oldest, instead of
+    /// the newest ones
+    #[clap(long)]
+    sample: Option<usize>,
+    /// Only list the dependents whose requirement accepts this version
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "print-bound-only",
+            "best",
+            "min",
+            "as-requirement",
+            "pin-dry-run",
+            "emit",
+            "joint",
+            "fail-if-newer-exists",
+        ]
+    )]
+    dependents_of_version: Option<Version>,
+    /// Only print the requirement merged from the dependents, without looking up the
+    /// versions of the crate
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "best",
+            "min",
+            "as-requirement",
+            "pin-dry-run",
+            "emit",
+            "joint",
+            "fail-if-newer-exists",
+        ]
+    )]
+    print_bound_only: bool,
+    /// Only print whether any compatible version exists
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "best",
+            "min",
+            "as-requirement",
+            "pin-dry-run",
+            "dependents-of-version",
+            "print-bound-only",
+            "format",
+            "emit",
+        ]
+    )]
+    any: bool,
+    /// Check that the newest versions found for each dependency accept each other
+    #[clap(long)]
+    joint: bool,
+    /// Also write the versions found to a file as FORMAT=PATH, can be repeated
+    #[clap(long)]
+    emit: Vec<Emit>,
+    /// Fail if a compatible version newer than the one in the lockfile exists
+    #[clap(long)]
+    fail_if_newer_exists: bool,
+    /// Print how many lookups were served from the cache and how many were fetched
+    #[clap(long)]
+    stats: bool,
+    /// Fail if the lockfile isn't in this format version, 1 to 4 as in its `version` field
+    #[clap(long)]
+    lock_version: Option<u32>,
+    /// Dependencies to find compatible versions of
+    #[clap(required = true)]
+    dependencies: Vec<String>,
+    // Set when there is no lockfile and the manifest's requirements are used instead
+    #[clap(skip)]
+    from_manifest: bool,
+    // The lockfile as read from stdin, kept for anything that reads it again
+    #[clap(skip)]
+    stdin_lockfile: Option<String>,
+    // The dependents of each crate in the lockfile, built once the lockfile is loaded
+    #[clap(skip)]
+    dependent_index: Option<DependentIndex>,
 }
 
 impl Compat {
-    pub fn run(self) -> Result<()> {
-        let lock =
-            Lockfile::load(&self.path).map_err(|error| ConstError::CouldNotLoadLockFileError {
-                path: self.path,
-                error,
-            })?;
+    // As given on the command line, the config file can fill them in otherwise
+    pub fn count(&self) -> Option<&Count> {
+        self.count.as_ref()
+    }
+
+    pub fn include_yanked(&self) -> bool {
+        self.include_yanked
+    }
+
+    pub fn pre(&self) -> bool {
+        self.pre
+    }
+
+    pub fn kind(&self) -> DependencyKind {
+        self.kind
+    }
+
+    pub fn is_machine_readable(&self) -> bool {
+        matches!(
+            self.format,
+            Format::Json | Format::Plain | Format::Junit | Format::Toml
+        )
+    }
+
+    pub fn run(mut self) -> Result<()> {
+        let started = Instant::now();
+
+        self.check_junit_options()?;
+
+        let lock = self.load_lockfile()?;
+
+        self.check_lock_version(&lock)?;
+
+        self.dependent_index = Some(DependentIndex::new(&lock));
 
         let provider = Provider::new();
 
-        // Find the range and get all versions of the crate sorted
-        let ((lower_bound, upper_bound), versions) =
-            find_packed_bound(&provider, &self.dependency, &lock)?;
+        let result = self.run_with(&provider, &lock, started);
 
-        let count = match self.count {
-            Count::All => usize::MAX,
-            Count::Count(count) => count,
+        // Printed whether or not the run succeeded, a failing run is as worth looking into
+        if self.stats && !get_config().quiet {
+            self.display_stats(&provider);
+        }
+
+        result
+    }
+
+    // A JUnit report only says whether each crate resolves, so the options that pick
+    // something else to print or check have no place in it
+    fn check_junit_options(&self) -> Result<()> {
+        if self.format.ne(&Format::Junit) {
+            return Ok(());
+        }
+
+        let options = [
+            ("--min", self.min),
+            ("--pin-dry-run", self.pin_dry_run),
+            ("--print-bound-only", self.print_bound_only),
+            ("--emit", !self.emit.is_empty()),
+            ("--joint", self.joint),
+            ("--fail-if-newer-exists", self.fail_if_newer_exists),
+        ];
+
+        match options.into_iter().find(|(_, given)| *given) {
+            Some((option, _)) => Err(ConstError::JunitAndOptionError {
+                option: option.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn run_with(&self, provider: &Provider, lock: &Lockfile, started: Instant) -> Result<()> {
+        if self.format.eq(&Format::Junit) {
+            return self.report_junit(provider, lock, started);
+        }
+
+        if self.any {
+            return self.check_any(provider, lock);
+        }
+
+        if let Some(version) = &self.dependents_of_version {
+            for dependency in &self.dependencies {
+                self.display_accepting_dependents(provider, lock, dependency, version)?;
+            }
+
+            return Ok(());
+        }
+
+        let mut output = Output::new(self.renderer(&self.format, is_colored()));
+
+        if self.print_bound_only {
+            for dependency in &self.dependencies {
+                self.display_bound(provider, lock, dependency, &mut output)?;
+            }
+
+            return output.finish();
+        }
+
+        let max_version = self.rust_version_ceiling()?;
+
+        let mut selections = Vec::new();
+
+        let mut emitted = self
+            .emit
+            .iter()
+            .map(|emit| Output::to_file(self.renderer(&emit.format, false), emit.path.clone()))
+            .collect::<Vec<_>>();
+
+        let mut failed = 0;
+
+        for dependency in &self.dependencies {
+            // Cargo allows one version of a crate per semver compatible range, the
+            // dependents using each were resolved apart from the others so they are too
+            let locked_versions = find_locked_versions(dependency, lock);
+
+            let groups = match locked_versions.len() {
+                0 | 1 => vec![None],
+                _ => locked_versions.iter().map(Some).collect(),
+            };
+
+            for locked in groups {
+                let mut resolve = || -> Result<Selection> {
+                    if self.explain {
+                        explain_merge(
+                            provider,
+                            dependency,
+                            self.find_dependents(lock, dependency, locked)?,
+                        )?;
+                        eprintln!();
+                    }
+
+                    let selection = match self.select_versions(
+                        provider,
+                        lock,
+                        dependency,
+                        max_version.as_deref(),
+                        locked,
+                    ) {
+                        Err(error)
+                            if self.explain_unsatisfiable
+                                && !error.conflicting_dependents().is_empty() =>
+                        {
+                            explain_resolution(
+                                provider,
+                                dependency,
+                                self.find_dependents(lock, dependency, locked)?,
+                            )?;
+
+                            return Err(error);
+                        }
+                        result => result?,
+                    };
+
+                    self.present(
+                        lock,
+                        &selection,
+                        max_version.as_deref(),
+                        &mut output,
+                        &mut emitted,
+                        started,
+                    )?;
+
+                    Ok(selection)
+                };
+
+                // With several crates one failing doesn't stop the rest from being listed
+                match resolve() {
+                    Ok(selection) => selections.push(selection),
+                    Err(error) if self.dependencies.len().gt(&1) => {
+                        output.error(dependency, &error);
+                        failed += 1;
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+        }
+
+        let pinned = match self.pin_dry_run {
+            true => self.preview_pins(&selections, &mut output),
+            false => Ok(()),
+        };
+
+        // Written even when a crate failed, so the ones that were found are still listed
+        output.finish()?;
+
+        for emitted in emitted {
+            emitted.finish()?;
+        }
+
+        pinned?;
+
+        if self.joint {
+            check_joint_compatibility(provider, &selections)?;
+        }
+
+        if self.fail_if_newer_exists {
+            check_up_to_date(lock, &selections)?;
+        }
+
+        if failed.gt(&0) {
+            return Err(ConstError::DependenciesFailedError {
+                failed,
+                total: self.dependencies.len(),
+            });
+        }
+
+        Ok(())
+    }
+
+    // Find the versions of the crate that would be listed, newest first and already
+    // filtered and cut down to --count, without printing them
+    pub fn compatible_versions(
+        &self,
+        provider: &Provider,
+        lock: &Lockfile,
+        dependency: &str,
+    ) -> Result<Vec<ParsedVersion>> {
+        let max_version = self.rust_version_ceiling()?;
+
+        let selection =
+            self.select_versions(provider, lock, dependency, max_version.as_deref(), None)?;
+
+        Ok(self
+            .listed_versions(&selection)
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+
+    // Print the versions selected for a crate in the way asked for and add them to the
+    // output of each --emit
+    fn present(
+        &self,
+        lock: &Lockfile,
+        selection: &Selection,
+        max_version: Option<&str>,
+        output: &mut Output,
+        emitted: &mut [Output],
+        started: Instant,
+    ) -> Result<()> {
+        let report = self.report(selection, started);
+
+        // The logger prints them for text, as they come
+        if self.is_machine_readable() && get_config().warnings {
+            for warning in &report.warnings {
+                output.warning(report.crate_name, warning);
+            }
+        }
+
+        // The other formats list the one version left by listed_versions as usual with
+        // --at-least and --min
+        if self.best {
+            self.display_best(selection, max_version)?;
+        } else if let (Some(at_least), false) = (&self.at_least, self.is_machine_readable()) {
+            self.display_lowest(selection, at_least)?;
+        } else if self.as_requirement {
+            println!(
+                "{} = \"{}\"",
+                selection.crate_name,
+                simplest_requirement(&selection.bound)
+            );
+        } else if self.min && !self.is_machine_readable() {
+            self.display_min(selection)?;
+        } else if !self.pin_dry_run {
+            // Fails the same way as the text output when there is no lowest version
+            if self.at_least.is_some() {
+                self.lowest(selection)?;
+            }
+
+            // Pins are previewed together once every crate is resolved
+            output.push(output.renderer().render_versions(&report)?);
+        }
+
+        if self.workspace && !get_config().quiet {
+            self.display_members(lock, &selection.crate_name)?;
+        }
+
+        for emitted in emitted {
+            emitted.push(emitted.renderer().render_versions(&report)?);
+        }
+
+        Ok(())
+    }
+
+    fn display_stats(&self, provider: &Provider) {
+        let stats = provider.stats();
+
+        eprintln!();
+
+        eprint_header_and_items(
+            "Cache statistics",
+            [
+                format!("served from the cache: {}", stats.hits),
+                format!("fetched: {}", stats.misses),
+                format!("requests sent, counting retries: {}", stats.requests),
+            ],
+        );
+    }
+
+    // Catches a lockfile written by another cargo in an older or newer format before
+    // anything is fetched
+    fn check_lock_version(&self, lock: &Lockfile) -> Result<()> {
+        let Some(expected) = self.lock_version else {
+            return Ok(());
         };
 
-        let versions = versions.iter().take(upper_bound).skip(lower_bound).rev(); // Display later versions first
+        ResolveVersion::try_from(expected)
+            .map_err(|_| ConstError::InvalidLockVersionArgument { argument: expected })?;
+
+        if self.from_manifest {
+            print_warning("There is no lockfile to check the format version of");
+            return Ok(());
+        }
+
+        let found = u32::from(lock.version);
+
+        if found.ne(&expected) {
+            return Err(ConstError::LockVersionMismatchError {
+                path: self.path.clone(),
+                expected,
+                found,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Without a lockfile the requirements in the manifest next to where it would be are
+    // used instead, which only covers the project's own direct dependencies
+    fn load_lockfile(&mut self) -> Result<Lockfile> {
+        if self.path.eq(STDIN_PATH) {
+            let mut contents = String::new();
+
+            std::io::stdin()
+                .read_to_string(&mut contents)
+                .map_err(ConstError::ReadStdinError)?;
+
+            let lock =
+                Lockfile::from_str(&contents).map_err(ConstError::StdinLockFileParseError)?;
 
-        let versions = versions.filter(|version| self.include_yanked || !version.yanked);
+            self.stdin_lockfile = Some(contents);
 
-        if versions.clone().peekable().peek().is_none() {
-            return Err(ConstError::OnlyYankedVersionExistsError {
-                crate_name: self.dependency,
+            return Ok(lock);
+        }
+
+        if Path::new(&self.path).exists() {
+            return Lockfile::load(&self.path).map_err(|error| {
+                ConstError::CouldNotLoadLockFileError {
+                    path: self.path.clone(),
+                    error,
+                }
+            });
+        }
+
+        let manifest_path = get_manifest_path(&self.path);
+
+        if !manifest_path.is_file() {
+            return Err(ConstError::NoLockfileOrManifestError {
+                lock_path: self.path.clone(),
+                manifest_path: manifest_path.to_string_lossy().to_string(),
+            });
+        }
+
+        let message = format!(
+            "No lockfile at {}, using the requirements in {} which leaves out every dependent \
+            but the project itself",
+            self.path,
+            manifest_path.to_string_lossy()
+        );
+
+        print_warning(&message);
+
+        self.from_manifest = true;
+
+        Ok(Lockfile {
+            version: Default::default(),
+            packages: Vec::new(),
+            root: None,
+            metadata: Default::default(),
+            patch: Default::default(),
+        })
+    }
+
+    // The project as the only dependent, with each requirement on the crate in its manifest
+    fn manifest_constraints(&self, dependency: &str) -> Result<Vec<DependentConstraint>> {
+        let manifest_path = self.manifest_path()?;
+
+        let package = get_package_id(&manifest_path)?;
+
+        let requirements = get_dependency_requirements(&manifest_path, dependency)?;
+
+        if requirements.is_empty() {
+            return Err(ConstError::DependencyNotInManifestError {
+                crate_name: dependency.to_string(),
+                path: manifest_path.to_string_lossy().to_string(),
             });
         }
 
-        let versions: Box<dyn Iterator<Item = _>> = if let Some(version_str) = &self.max_version {
-            if let Some(version) = get_rust_version(&version_str) {
-                let versions = versions.filter(move |crate_version| {
-                    if let Some(ref crate_rust_version) = crate_version.rust_version {
-                        if let Some(crate_rust_version) = get_rust_version(crate_rust_version) {
-                            crate_rust_version.le(&version)
-                        } else {
-                            true
+        let constraints = requirements
+            .into_iter()
+            .filter(|(kind, _)| self.kind.includes(kind))
+            .map(|(_, requirement)| {
+                let version_req =
+                    VersionReq::parse(&requirement).map_err(ConstError::VersionReqParseError)?;
+
+                let bound = Bound::try_from(&version_req).map_err(|error| match error {
+                    ConstError::NonOverlappingBoundsError { version_req, .. } => {
+                        ConstError::NonOverlappingBoundsError {
+                            version_req,
+                            crate_name: package.0.clone(),
+                            crate_version: package.1.clone(),
                         }
-                    } else {
-                        true
                     }
-                });
+                    error => error,
+                })?;
 
-                if versions.clone().peekable().peek().is_none() {
-                    return Err(ConstError::UnsatisfiableMaxRustVersionError(
-                        version_str.to_owned(),
-                    ));
-                }
+                Ok((package.clone(), bound, version_req))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Only requirements of other kinds were found
+        if constraints.is_empty() {
+            return Err(ConstError::NoMatchingDependentError(dependency.to_string()));
+        }
+
+        Ok(constraints)
+    }
+
+    // Answer whether a compatible version exists for every dependency without listing
+    // or filtering them any further
+    fn check_any(&self, provider: &Provider, lock: &Lockfile) -> Result<()> {
+        let mut not_found = Vec::new();
+
+        for dependency in &self.dependencies {
+            let found = match self.find_packed_bound(provider, dependency, lock, None) {
+                Ok(packed_bound) => packed_bound
+                    .in_range()
+                    .iter()
+                    .any(|version| get_config().include_yanked || !version.yanked),
+                Err(error) if error.is_unsatisfiable() => false,
+                Err(error) => return Err(error),
+            };
+
+            let answer = if found { "yes" } else { "no" };
 
-                Box::new(versions)
+            if self.dependencies.len().gt(&1) {
+                println!("{}: {}", dependency, answer);
             } else {
-                return Err(ConstError::InvalidMaxRustVersionError(
-                    version_str.to_owned(),
-                ));
+                println!("{}", answer);
+            }
+
+            if !found {
+                not_found.push(dependency.clone());
             }
+        }
+
+        if not_found.is_empty() {
+            Ok(())
+        } else {
+            Err(ConstError::NoCompatibleVersionError {
+                crate_names: not_found,
+            })
+        }
+    }
+
+    // Every dependency is a test case that passes when a non-yanked version is found, the
+    // report is printed whole before failing the way the other formats would
+    fn report_junit(&self, provider: &Provider, lock: &Lockfile, started: Instant) -> Result<()> {
+        let max_version = self.rust_version_ceiling()?;
+
+        let renderer = JunitRenderer;
+
+        let mut test_cases = Vec::new();
+        let mut errors = Vec::new();
+
+        for dependency in &self.dependencies {
+            let result = self
+                .select_versions(provider, lock, dependency, max_version.as_deref(), None)
+                .and_then(|selection| {
+                    let report = self.report(&selection, started);
+
+                    if get_config().warnings {
+                        for warning in &report.warnings {
+                            eprintln!("{}", renderer.render_warning(dependency, warning));
+                        }
+                    }
+
+                    match selection.versions.iter().any(|version| !version.yanked) {
+                        true => renderer.render_versions(&report),
+                        false => Err(only_yanked_error(dependency, &selection.versions)),
+                    }
+                });
+
+            let test_case = result.unwrap_or_else(|error| {
+                let test_case = renderer.render_error(dependency, &error);
+                errors.push(error);
+                test_case
+            });
+
+            test_cases.push(test_case);
+        }
+
+        println!("{}", renderer.render_document(&test_cases));
+
+        match errors.len() {
+            0 => Ok(()),
+            1 if self.dependencies.len().eq(&1) => Err(errors.remove(0)),
+            failed => Err(ConstError::DependenciesFailedError {
+                failed,
+                total: self.dependencies.len(),
+            }),
+        }
+    }
+
+    fn find_packed_bound(
+        &self,
+        provider: &Provider,
+        dependency: &str,
+        lock: &Lockfile,
+        locked: Option<&Version>,
+    ) -> Result<PackedBound> {
+        let merged_bound = self.merge_dependents(provider, dependency, lock, locked)?;
+
+        match_versions(provider, dependency, merged_bound)
+    }
+
+    // The bound from the dependents' requirements, raised to --at-least if given
+    fn merge_dependents(
+        &self,
+        provider: &Provider,
+        dependency: &str,
+        lock: &Lockfile,
+        locked: Option<&Version>,
+    ) -> Result<MergedBound> {
+        self.check_source(lock, dependency)?;
+
+        self.check_manifest(lock, dependency)?;
+
+        let mut merged_bound = if self.from_manifest {
+            merge_constraints(dependency, self.manifest_constraints(dependency)?)?
         } else {
-            Box::new(versions)
+            self.merge_lock_dependents(provider, dependency, lock, locked)?
         };
 
-        let versions = versions.take(count).map(|version| {
-            let min_rust_version_message = version
-                .rust_version
-                .as_ref()
-                .map(|version| format!("    min-rust-version = {}", version))
-                .unwrap_or_default();
-            format!("{}{}", &version.num, min_rust_version_message)
-        });
+        if let Some(at_least) = &self.at_least {
+            raise_lower_bound(dependency, &mut merged_bound, at_least)?;
+        }
 
-        print_header_and_items("Compatible versions found", versions);
+        Ok(merged_bound)
+    }
+
+    fn merge_lock_dependents(
+        &self,
+        provider: &Provider,
+        dependency: &str,
+        lock: &Lockfile,
+        locked: Option<&Version>,
+    ) -> Result<MergedBound> {
+        let dependents = self.find_dependents(lock, dependency, locked)?;
+
+        let merged_bound = if self.cache_resolution {
+            let mut features = self.features.clone();
+
+            // The order and repeats of --features don't change what is built
+            features.sort();
+            features.dedup();
+
+            let key = ResolutionKey {
+                crate_name: dependency.to_string(),
+                dependents,
+                kind: get_config().kind,
+                features,
+                all_features: self.all_features,
+                no_default_features: self.no_default_features,
+                package: self.package.clone(),
+                pre: get_config().pre,
+            };
+
+            merge_dependents_cached(provider, key)?
+        } else {
+            merge_dependents(provider, dependency, dependents)?
+        };
+
+        Ok(merged_bound)
+    }
+
+    // The versions on crates.io say little about a crate taken from git, so it is only
+    // resolved against them when asked to
+    fn check_source(&self, lock: &Lockfile, dependency: &str) -> Result<()> {
+        let from_git = lock.packages.iter().any(|package| {
+            package.name.as_str().eq(dependency)
+                && package
+                    .source
+                    .as_ref()
+                    .is_some_and(|source| source.is_git())
+        });
+
+        if !from_git {
+            return Ok(());
+        }
+
+        if !self.treat_as_registry {
+            return Err(ConstError::GitSourceError {
+                crate_name: dependency.to_string(),
+            });
+        }
+
+        let message = format!(
+            "{} comes from git in the lockfile, the versions found on crates.io are only a guide \
+            for switching back to it",
+            dependency
+        );
+
+        print_warning(&message);
+
+        Ok(())
+    }
+
+    // A requirement in the manifest that no locked version meets means the manifest was
+    // edited without updating the lockfile, so the dependents found in it are out of date
+    fn check_manifest(&self, lock: &Lockfile, dependency: &str) -> Result<()> {
+        let manifest_path = self.manifest_path()?;
+
+        // Nothing to compare against when only the lockfile is around
+        if !manifest_path.is_file() {
+            return Ok(());
+        }
+
+        let locked = lock
+            .packages
+            .iter()
+            .filter(|package| package.name.as_str().eq(dependency))
+            .map(|package| &package.version)
+            .collect::<Vec<_>>();
+
+        // A crate missing from the lockfile is reported once the dependents are looked for
+        if locked.is_empty() {
+            return Ok(());
+        }
+
+        for (_, requirement) in get_dependency_requirements(&manifest_path, dependency)? {
+            let Ok(version_req) = VersionReq::parse(&requirement) else {
+                continue;
+            };
+
+            if locked.iter().any(|version| version_req.matches(version)) {
+                continue;
+            }
+
+            let locked = locked
+                .iter()
+                .map(|version| version.to_string())
+                .collect::<Vec<_>>();
+
+            let message = format!(
+                "The requirement {} on {} in {} is not met by the locked version{} {}, the \
+                lockfile may be out of date",
+                requirement,
+                dependency,
+                manifest_path.to_string_lossy(),
+                if locked.len() == 1 { "" } else { "s" },
+                locked.join(", ")
+            );
+
+            print_warning(&message);
+        }
 
         Ok(())
     }
+
+    // Selecting features needs the resolved feature graph from cargo metadata, otherwise
+    // every dependency edge in the lockfile is used, either way only the dependents
+    // reachable from the selected member are kept. With `locked` only the dependents the
+    // lockfile resolved to that version of the crate are kept
+    fn find_dependents(
+        &self,
+        lock: &Lockfile,
+        dependency: &str,
+        locked: Option<&Version>,
+    ) -> Result<Vec<(String, String)>> {
+        // Without a lockfile the manifest's own dependencies are all there is to check
+        if !self.from_manifest {
+            check_in_lockfile(dependency, lock)?;
+        }
+
+        let member = self
+            .package
+            .as_deref()
+            .map(|package| self.find_member(package))
+            .transpose()?;
+
+        let dependents =
+            if self.features.is_empty() && !self.all_features && !self.no_default_features {
+                match &self.dependent_index {
+                    Some(index) => index.dependents_of(dependency).to_vec(),
+                    None => find_dependents(dependency, lock),
+                }
+            } else {
+                let selection = FeatureSelection {
+                    features: &self.features,
+                    all_features: self.all_features,
+                    no_default_features: self.no_default_features,
+                };
+
+                // Features apply to the package of the manifest cargo metadata is run on
+                let manifest_path = member
+                    .as_ref()
+                    .map(|member| member.manifest_path.clone())
+                    .unwrap_or_else(|| get_manifest_path(&self.path));
+
+                find_dependents_from_metadata(&manifest_path, dependency, &selection)?
+            };
+
+        let dependents = match locked {
+            Some(locked) => filter_locked_to(dependents, lock, dependency, locked),
+            None => dependents,
+        };
+
+        let Some(member) = member else {
+            return Ok(dependents);
+        };
+
+        let reachable = find_reachable(&member.name, lock);
+
+        Ok(dependents
+            .into_iter()
+            .filter(|dependent| reachable.contains(dependent))
+            .collect())
+    }
+
+    fn find_member(&self, package: &str) -> Result<WorkspaceMember> {
+        let members = get_workspace_members(&get_manifest_path(&self.path))?;
+
+        let names = members
+            .iter()
+            .map(|member| member.name.clone())
+            .collect::<Vec<_>>();
+
+        members
+            .into_iter()
+            .find(|member| member.name.eq(package))
+            .ok_or_else(|| ConstError::UnknownWorkspaceMemberError {
+                package: package.to_string(),
+                members: names,
+            })
+    }
+
+    // The max rust version to filter by, taken from --rust-version-source when
+    // --max-version isn't given, --best falls back to the installed rustc
+    fn rust_version_ceiling(&self) -> Result<Option<String>> {
+        if self.max_version.is_some() {
+            return Ok(self.max_version.clone());
+        }
+
+        let sources = match self.rust_version_source {
+            Some(RustVersionSource::Auto) => vec![
+                RustVersionSource::Toolchain,
+                RustVersionSource::Manifest,
+                RustVersionSource::Rustc,
+            ],
+            Some(source) => vec![source],
+            None if self.auto_msrv => vec![RustVersionSource::Toolchain, RustVersionSource::Rustc],
+            None if self.best => vec![RustVersionSource::Rustc],
+            None => return Ok(None),
+        };
+
+        for source in &sources {
+            let (version, from) = match source {
+                RustVersionSource::Toolchain => (
+                    get_toolchain_rust_version(&self.manifest_path()?)?,
+                    "rust-toolchain.toml",
+                ),
+                RustVersionSource::Manifest => {
+                    let manifest_path = self.manifest_path()?;
+
+                    // A lone lockfile has no manifest to read
+                    let version = match manifest_path.is_file() {
+                        true => get_manifest_rust_version(&manifest_path)?,
+                        false => None,
+                    };
+
+                    (version, "Cargo.toml")
+                }
+                RustVersionSource::Rustc => (get_rustc_version(), "rustc"),
+                RustVersionSource::Auto => unreachable!("auto is expanded above"),
+            };
+
+            if let Some(version) = version {
+                log::info!("Using rust {} from {}", version, from);
+                return Ok(Some(version));
+            }
+        }
+
+        print_warning("Could not detect the rust version, versions are not filtered by it");
+
+        Ok(None)
+    }
+
+    // The manifest of the --package member, or the one next to the lockfile
+    fn manifest_path(&self) -> Result<PathBuf> {
+        match &self.package {
+            Some(package) => Ok(self.find_member(package)?.manifest_path),
+            None => Ok(get_manifest_path(&self.path)),
+        }
+    }
+
+    // Find the versions of the crate that satisfy the dependents and the filters
+    fn select_versions(
+        &self,
+        provider: &Provider,
+        lock: &Lockfile,
+        dependency: &str,
+        max_version: Option<&str>,
+        locked: Option<&Version>,
+    ) -> Result<Selection> {
+        // Only versions present in the lockfile can have an entry, the rest fall back to
+        // what crates.io reports
+        let lockfile_rust_versions = if self.lockfile_msrv {
+            match &self.stdin_lockfile {
+                Some(contents) => parse_lockfile_rust_versions(STDIN_PATH, contents, dependency)?,
+                None => get_lockfile_rust_versions(&self.path, dependency)?,
+            }
+        } else {
+            HashMap::new()
+        };
+
+        // Find the range and get all versions of the crate sorted
+        let packed_bound = match self.find_packed_bound(provider, dependency, lock, locked) {
+            Err(error) if self.allow_downgrade && error.is_unsatisfiable() => {
+                self.downgrade(provider, lock, dependency, locked, error)?
+            }
+            result => result?,
+        };
+
+        let versions = packed_bound
+            .in_range()
+            .iter()
+            .rev() // Display later versions first
+            .cloned()
+            .map(|mut version| {
+                if let Some(rust_version) = lockfile_rust_versions.get(&version.num.to_string()) {
+                    version.rust_version = Some(rust_version.clone());
+                }
+                version
+            });
+
+        let versions = versions
+            .filter(|version| get_config().include_yanked || !version.yanked)
+            .collect::<Vec<_>>();
+
+        if versions.is_empty() {
+            if self.explain_yanked || is_verbose(Verbosity::Info) {
+                let yanked = packed_bound
+                    .in_range()
+                    .iter()
+                    .rev()
+                    .map(|version| format!("{}    yanked, excluded", version.num));
+
+                eprint_header_and_items(
+                    &format!("Versions of {} within the bound", dependency),
+                    yanked,
+                );
+                eprintln!();
+            }
+
+            return Err(only_yanked_error(
+                dependency,
+                packed_bound.in_range().iter().rev(),
+            ));
+        }
+
+        // The bound is the one interval every requirement falls within, semver still skips
+        // some versions inside it, prereleases unless a comparator names the same version
+        let allowed = if self.pre {
+            exclude_unnamed_prereleases(
+                &packed_bound.bound,
+                &packed_bound.dependents,
+                packed_bound.in_range(),
+            )
+        } else {
+            BoundSet::from(packed_bound.bound.clone())
+        };
+
+        let versions = versions
+            .into_iter()
+            .filter(|version| allowed.contains(&version.num))
+            .collect::<Vec<_>>();
+
+        if versions.is_empty() {
+            return Err(ConstError::ExcludedByRequirementsError {
+                crate_name: dependency.to_string(),
+            });
+        }
+
+        let versions = match self.min_stability {
+            Some(min_stability) => {
+                let versions = versions
+                    .into_iter()
+                    .filter(|version| Stability::of(&version.num).ge(&min_stability))
+                    .collect::<Vec<_>>();
+
+                if versions.is_empty() {
+                    return Err(ConstError::UnsatisfiableStabilityError {
+                        crate_name: dependency.to_string(),
+                        stability: format!("{:?}", min_stability).to_lowercase(),
+                    });
+                }
+
+                versions
+            }
+            None => versions,
+        };
+
+        // Narrowed to where the --matching range overlaps the dependents' bound
+        // A bare * has no comparators and takes in every version
+        let versions = match self
+            .matching
+            .as_ref()
+            .filter(|req| !req.comparators.is_empty())
+        {
+            Some(matching) => {
+                let no_match = || ConstError::NoVersionMatchingError {
+                    crate_name: dependency.to_string(),
+                    requirement: matching.to_string(),
+                };
+
+                let matching_bound = match Bound::try_from(matching) {
+                    Ok(bound) => bound,
+                    Err(ConstError::NonOverlappingBoundsError { .. }) => return Err(no_match()),
+                    Err(ConstError::VersionError(comparator, _, reason)) => {
+                        return Err(ConstError::VersionError(
+                            comparator,
+                            dependency.to_string(),
+                            reason,
+                        ))
+                    }
+                    Err(error) => return Err(error),
+                };
+
+                let overlap = packed_bound
+                    .bound
+                    .intersect(&matching_bound)
+                    .map_err(|_| no_match())?;
+
+                let versions = versions
+                    .into_iter()
+                    .filter(|version| overlap.contains(&version.num))
+                    .collect::<Vec<_>>();
+
+                if versions.is_empty() {
+                    return Err(no_match());
+                }
+
+                versions
+            }
+            None => versions,
+        };
+
+        let in_range = packed_bound.in_range();
+
+        let range = (
+            in_range.first().unwrap().num.clone(),
+            in_range.last().unwrap().num.clone(),
+        );
+
+        let versions = if let Some(version_str) = max_version {
+            let version = get_rust_version(version_str).map_err(|reason| {
+                ConstError::InvalidMaxRustVersionError {
+                    version: version_str.to_owned(),
+                    reason,
+                }
+            })?;
+
+            // Versions that don't say which rust they need, or say it in a way that can't
+            // be read, are kept
+            let versions = versions
+                .into_iter()
+                .filter(|crate_version| {
+                    match crate_version.rust_version.as_deref().map(get_rust_version) {
+                        Some(Ok(crate_rust_version)) => crate_rust_version.le(&version),
+                        _ => true,
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            if versions.is_empty() {
+                return Err(ConstError::UnsatisfiableMaxRustVersionError(
+                    version_str.to_owned(),
+                ));
+            }
+
+            versions
+        } else {
+            versions
+        };
+
+        Ok(Selection {
+            crate_name: dependency.to_string(),
+            versions,
+            dependents: packed_bound.dependents,
+            range,
+            bound: packed_bound.bound,
+            allowed,
+            lower_index: packed_bound.lower_index,
+            upper_index: packed_bound.upper_index,
+            locked: locked.cloned(),
+        })
+    }
+
+    // Look for a dependent to downgrade when the bound can't be satisfied, printing it
+    // along with the versions found with it or failing with the original error
+    fn downgrade(
+        &self,
+        provider: &Provider,
+        lock: &Lockfile,
+        dependency: &str,
+        locked: Option<&Version>,
+        error: ConstError,
+    ) -> Result<PackedBound> {
+        let dependents = self.find_dependents(lock, dependency, locked)?;
+
+        let found = find_downgrade(
+            provider,
+            dependency,
+            &dependents,
+            &error.conflicting_dependents(),
+        )?;
+
+        let Some((downgrade, packed_bound)) = found else {
+            return Err(error);
+        };
+
+        eprint_header_and_items(
+            &format!("Downgrade that resolves {}", dependency),
+            [format!(
+                "{} {} -> {}",
+                downgrade.name, downgrade.from, downgrade.to
+            )],
+        );
+        eprintln!();
+
+        Ok(packed_bound)
+    }
+
+    // The workspace members the crate is reachable from in the lockfile, with the tables
+    // of their manifests it is declared in when they use it directly
+    fn display_members(&self, lock: &Lockfile, dependency: &str) -> Result<()> {
+        let members = get_workspace_members(&get_manifest_path(&self.path))?;
+
+        let mut lines = Vec::new();
+
+        for member in members {
+            let uses = find_reachable(&member.name, lock)
+                .iter()
+                .any(|(name, _)| name.eq(dependency));
+
+            if !uses {
+                continue;
+            }
+
+            let tables = get_dependency_tables(&member.manifest_path, dependency)?;
+
+            let how = match tables.is_empty() {
+                true => "through its dependencies".to_string(),
+                false => tables.join(", "),
+            };
+
+            lines.push(format!("{}    {}", member.name, how));
+        }
+
+        eprintln!();
+
+        if lines.is_empty() {
+            eprintln!("No workspace member uses {}", dependency);
+        } else {
+            eprint_header_and_items(&format!("Workspace members using {}", dependency), lines);
+        }
+
+        Ok(())
+    }
+
+    fn renderer(&self, format: &Format, colored: bool) -> Box<dyn Renderer> {
+        match format {
+            Format::Text => Box::new(TextRenderer {
+                colored,
+                named: self.dependencies.len().gt(&1),
+                annotated: !self.output_versions_only,
+            }),
+            Format::Plain => Box::new(PlainRenderer),
+            Format::Json => Box::new(JsonRenderer {
+                named: self.dependencies.len().gt(&1),
+                include_requirements: self.json_include_requirements,
+            }),
+            Format::Junit => Box::new(JunitRenderer),
+            Format::Toml => Box::new(TomlRenderer {
+                include_requirements: self.json_include_requirements,
+            }),
+        }
+    }
+
+    // The selected versions that are listed, only the oldest with --min or --at-least,
+    // spread out by --sample or the newest ones up to --count
+    fn listed_versions<'a>(&self, selection: &'a Selection) -> Vec<&'a ParsedVersion> {
+        if self.min {
+            return selection.versions.last().into_iter().collect();
+        }
+
+        if self.at_least.is_some() {
+            return self.lowest(selection).into_iter().collect();
+        }
+
+        let versions = self.sorted(&selection.versions);
+
+        match self.sample {
+            Some(sample) => spread(&versions, sample).into_iter().copied().collect(),
+            None => versions
+                .into_iter()
+                .take(get_config().count.limit())
+                .collect(),
+        }
+    }
+
+    // The versions in --sort order, they come newest first
+    fn sorted<'a>(&self, versions: &'a [ParsedVersion]) -> Vec<&'a ParsedVersion> {
+        let mut versions = versions.iter().collect::<Vec<_>>();
+
+        match self.sort {
+            Sort::Desc => {}
+            Sort::Asc => versions.reverse(),
+            // Stable, so versions needing the same rust stay newest first
+            Sort::Msrv => versions.sort_by_key(|version| {
+                let rust_version = version
+                    .rust_version
+                    .as_deref()
+                    .and_then(|rust_version| get_rust_version(rust_version).ok());
+
+                (rust_version.is_none(), rust_version)
+            }),
+        }
+
+        versions
+    }
+
+    // Also takes the warnings printed since the last report, so each goes to the crate
+    // being resolved when it was printed
+    fn report<'a>(&self, selection: &'a Selection, started: Instant) -> CompatReport<'a> {
+        let versions = self.listed_versions(selection);
+
+        CompatReport {
+            crate_name: &selection.crate_name,
+            bound: ReportBound {
+                requirement: selection.allowed.to_string(),
+                oldest: &selection.range.0,
+                newest: &selection.range.1,
+            },
+            versions,
+            dependents: selection.dependents.iter().map(Into::into).collect(),
+            lower_dependent: (&selection.dependents[selection.lower_index]).into(),
+            upper_dependent: (&selection.dependents[selection.upper_index]).into(),
+            locked: selection.locked.as_ref(),
+            warnings: take_warnings(),
+            elapsed_ms: started.elapsed().as_millis(),
+        }
+    }
+
+    // Merging only needs the dependents' requirements, so the versions aren't fetched
+    fn display_bound(
+        &self,
+        provider: &Provider,
+        lock: &Lockfile,
+        dependency: &str,
+        output: &mut Output,
+    ) -> Result<()> {
+        let merged_bound = self.merge_dependents(provider, dependency, lock, None)?;
+
+        let bound = output
+            .renderer()
+            .render_bound(dependency, &VersionReq::from(&merged_bound.bound))?;
+
+        output.push(bound);
+
+        Ok(())
+    }
+
+    // The dependents that would be satisfied if `version` was picked, whether or not the
+    // others would be
+    fn display_accepting_dependents(
+        &self,
+        provider: &Provider,
+        lock: &Lockfile,
+        dependency: &str,
+        version: &Version,
+    ) -> Result<()> {
+        let dependents = self.find_dependents(lock, dependency, None)?;
+
+        let constraints = collect_constraints(provider, dependency, dependents)?;
+
+        let accepting = constraints
+            .iter()
+            .filter(|(_, _, version_req)| version_req.matches(version))
+            .map(|((name, crate_version), _, version_req)| {
+                format!("{} {} requires {}", name, crate_version, version_req)
+            });
+
+        print_header_and_items(
+            &format!("Dependents accepting {} {}", dependency, version),
+            accepting,
+        );
+
+        Ok(())
+    }
+
+    fn display_best(&self, selection: &Selection, max_version: Option<&str>) -> Result<()> {
+        let best = selection
+            .versions
+            .iter()
+            .find(|version| !version.yanked)
+            .ok_or_else(|| only_yanked_error(&selection.crate_name, &selection.versions))?;
+
+        let rust_version = max_version
+            .map(|version| format!("rust {}", version))
+            .unwrap_or_else(|| "any rust version".to_string());
+
+        print_header_and_items(
+            &format!("Best version of {} found", selection.crate_name),
+            [best.num.to_string()],
+        );
+
+        println!(
+            "newest non-yanked version between {} and {} that supports {}",
+            selection.range.0, selection.range.1, rust_version
+        );
+
+        Ok(())
+    }
+
+    // The oldest version left after filtering that isn't yanked, unless yanked ones are
+    // included
+    fn lowest<'a>(&self, selection: &'a Selection) -> Result<&'a ParsedVersion> {
+        selection
+            .versions
+            .iter()
+            .rev()
+            .find(|version| get_config().include_yanked || !version.yanked)
+            .ok_or_else(|| only_yanked_error(&selection.crate_name, &selection.versions))
+    }
+
+    fn display_lowest(&self, selection: &Selection, at_least: &Version) -> Result<()> {
+        let lowest = self.lowest(selection)?;
+
+        print_header_and_items(
+            &format!(
+                "Lowest version of {} from {} up found",
+                selection.crate_name, at_least
+            ),
+            [lowest.num.to_string()],
+        );
+
+        Ok(())
+    }
+
+    // Versions are newest first so the minimum is the last one left after filtering
+    fn display_min(&self, selection: &Selection) -> Result<()> {
+        let min = selection
+            .versions
+            .last()
+            .ok_or_else(|| ConstError::NoMinimumVersionError {
+                crate_name: selection.crate_name.clone(),
+            })?;
+
+        print_header_and_items(
+            &format!("Minimum version of {} found", selection.crate_name),
+            [min.num.to_string()],
+        );
+
+        Ok(())
+    }
+
+    // Every crate is previewed before failing, so one run shows all the pins to update
+    fn preview_pins(&self, selections: &[Selection], output: &mut Output) -> Result<()> {
+        let manifest_path = get_manifest_path(&self.path);
+
+        let path = manifest_path.to_string_lossy().to_string();
+
+        let mut out_of_date = Vec::new();
+
+        for selection in selections {
+            // Versions are listed newest first so the first one is the one to pin
+            let version = selection.versions[0].num.to_string();
+
+            let (old, new) = pin_dependency(&manifest_path, &selection.crate_name, &version)?;
+
+            let diff = unified_diff(&path, &old, &new);
+
+            let pin = PinPreview {
+                name: &selection.crate_name,
+                version,
+                path: path.clone(),
+                up_to_date: diff.is_empty(),
+                diff,
+            };
+
+            output.push(output.renderer().render_pin(&pin)?);
+
+            if !pin.up_to_date {
+                out_of_date.push((pin.name.to_string(), pin.version));
+            }
+        }
+
+        if out_of_date.is_empty() {
+            return Ok(());
+        }
+
+        Err(ConstError::PinOutOfDateError {
+            pins: out_of_date,
+            path,
+        })
+    }
+}
+
+// The crates were resolved independently, so check that the newest version picked for
+// each one accepts at least one of the versions found for every other crate it depends on
+fn check_joint_compatibility(provider: &Provider, selections: &[Selection]) -> Result<()> {
+    let kind = get_config().kind;
+
+    let mut conflicts = Vec::new();
+
+    for selection in selections {
+        let chosen = selection.versions[0].num.to_string();
+
+        let dependencies = provider.get_dependencies(&selection.crate_name, &chosen)?;
+
+        for dependency in dependencies.dependencies {
+            if !accepts_a_selected_version(&dependency, selections, kind) {
+                conflicts.push((
+                    (selection.crate_name.clone(), chosen.clone()),
+                    dependency.version_req,
+                    dependency.crate_id,
+                ));
+            }
+        }
+    }
+
+    if conflicts.is_empty() {
+        Ok(())
+    } else {
+        Err(ConstError::JointConflictError { conflicts })
+    }
+}
+
+// Only dependencies of the kinds --kind picks count, and one on a crate that isn't being
+// checked is always accepted. A crate locked at several versions has a selection for
+// each, a version from any of them will do
+fn accepts_a_selected_version(
+    dependency: &ParsedDependency,
+    selections: &[Selection],
+    kind: DependencyKind,
+) -> bool {
+    if !kind.includes(&dependency.kind) {
+        return true;
+    }
+
+    let mut others = selections
+        .iter()
+        .filter(|other| other.crate_name.eq(&dependency.crate_id))
+        .peekable();
+
+    others.peek().is_none()
+        || others
+            .flat_map(|other| other.versions.iter())
+            .any(|version| dependency.version_req.matches(&version.num))
+}
+
+// Name the yanked versions that would have been listed, so the error says what
+// --include-yanked would allow
+fn only_yanked_error<'a>(
+    crate_name: &str,
+    versions: impl IntoIterator<Item = &'a ParsedVersion>,
+) -> ConstError {
+    ConstError::OnlyYankedVersionExistsError {
+        crate_name: crate_name.to_string(),
+        versions: versions
+            .into_iter()
+            .filter(|version| version.yanked)
+            .map(|version| version.num.to_string())
+            .collect(),
+    }
+}
+
+// Pick `count` items evenly spaced from the first to the last, in their order
+fn spread<T>(items: &[T], count: usize) -> Vec<&T> {
+    match count {
+        0 => Vec::new(),
+        1 => items.iter().take(1).collect(),
+        count if count.ge(&items.len()) => items.iter().collect(),
+        count => (0..count)
+            .map(|index| &items[index * (items.len() - 1) / (count - 1)])
+            .collect(),
+    }
+}
+
+// The newest version found for each dependency must not be newer than the one locked,
+// with several versions locked the newest of them is compared
+fn check_up_to_date(lock: &Lockfile, selections: &[Selection]) -> Result<()> {
+    for selection in selections {
+        let current = selection.locked.as_ref().or_else(|| {
+            lock.packages
+                .iter()
+                .filter(|package| package.name.as_str().eq(&selection.crate_name))
+                .map(|package| &package.version)
+                .max()
+        });
+
+        let (Some(current), Some(newest)) = (current, selection.versions.first()) else {
+            continue;
+        };
+
+        if newest.num.gt(current) {
+            return Err(ConstError::NewerVersionExistsError {
+                crate_name: selection.crate_name.clone(),
+                current: current.to_string(),
+                newest: newest.num.to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::EXIT_INVALID_ARGUMENT;
+    use clap::ErrorKind;
+
+    fn parse(arguments: &[&str]) -> std::result::Result<Compat, clap::Error> {
+        Compat::try_parse_from(["compat", "helper"].iter().chain(arguments))
+    }
+
+    #[test]
+    fn invalid_count_suggests_valid_forms() {
+        let error = "five".parse::<Count>().unwrap_err();
+
+        assert_eq!(error.exit_code(), EXIT_INVALID_ARGUMENT);
+        assert_eq!(
+            error.to_string(),
+            "\"five\" is not a valid count, expected one of:-\n  \
+            all    list every compatible version\n  \
+            <N>    list at most N versions, from 1 up, e.g. --count 10"
+        );
+    }
+
+    #[test]
+    fn counts_parse_to_a_limit() {
+        assert_eq!("all".parse::<Count>().unwrap().limit(), usize::MAX);
+        assert_eq!("5".parse::<Count>().unwrap().limit(), 5);
+
+        for value in ["0", "abc", "-1", ""] {
+            assert!(
+                matches!(
+                    value.parse::<Count>(),
+                    Err(ConstError::InvalidCountArgument { argument }) if argument.eq(value)
+                ),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn output_modes_conflict() {
+        let conflicting: [&[&str]; 5] = [
+            &["--best", "--min"],
+            &["--at-least", "1.0.0", "--as-requirement"],
+            &["--min", "--pin-dry-run"],
+            &["--print-bound-only", "--best"],
+            &[
+                "--dependents-of-version",
+                "1.0.0",
+                "--emit",
+                "json=out.json",
+            ],
+        ];
+
+        for arguments in conflicting {
+            let error = parse(arguments).err().expect("the modes should conflict");
+
+            assert_eq!(error.kind(), ErrorKind::ArgumentConflict, "{:?}", arguments);
+        }
+    }
+
+    #[test]
+    fn format_conflicts_with_text_only_modes() {
+        for mode in [
+            &["--best"][..],
+            &["--as-requirement"],
+            &["--dependents-of-version", "1.0.0"],
+        ] {
+            let arguments = [&["--format", "json"][..], mode].concat();
+
+            let error = parse(&arguments)
+                .err()
+                .expect("the options should conflict");
+
+            assert_eq!(error.kind(), ErrorKind::ArgumentConflict, "{:?}", mode);
+        }
+
+        assert!(parse(&["--format", "json", "--min"]).is_ok());
+        assert!(parse(&["--format", "toml", "--at-least", "1.0.0"]).is_ok());
+    }
+
+    #[test]
+    fn junit_rejects_options_it_would_ignore() {
+        let compat = parse(&["--format", "junit", "--pin-dry-run"]).unwrap();
+
+        let error = compat.check_junit_options().unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "--format junit can't be used together with --pin-dry-run"
+        );
+        assert_eq!(error.exit_code(), EXIT_INVALID_ARGUMENT);
+
+        let compat = parse(&["--format", "junit", "--at-least", "1.0.0"]).unwrap();
+
+        assert!(compat.check_junit_options().is_ok());
+    }
+
+    #[test]
+    fn filters_combine_with_output_modes() {
+        assert!(parse(&["--print-bound-only", "--at-least", "1.0.0"]).is_ok());
+        assert!(parse(&["--best", "--matching", "1"]).is_ok());
+    }
+
+    // A project directory with the given files, and the --path of its lockfile
+    fn project(files: &[(&str, &str)]) -> (tempfile::TempDir, String) {
+        let directory = tempfile::tempdir().unwrap();
+
+        for (name, contents) in files {
+            std::fs::write(directory.path().join(name), contents).unwrap();
+        }
+
+        let lock_path = directory.path().join("Cargo.lock");
+
+        (directory, lock_path.to_string_lossy().to_string())
+    }
+
+    const TOOLCHAIN: (&str, &str) = ("rust-toolchain.toml", "[toolchain]\nchannel = \"1.75.0\"\n");
+
+    const MANIFEST: (&str, &str) = (
+        "Cargo.toml",
+        "[package]\nname = \"app\"\nversion = \"0.1.0\"\nrust-version = \"1.70\"\n",
+    );
+
+    fn ceiling(lock_path: &str, arguments: &[&str]) -> Option<String> {
+        let arguments = [&["--path", lock_path][..], arguments].concat();
+
+        parse(&arguments).unwrap().rust_version_ceiling().unwrap()
+    }
+
+    #[test]
+    fn max_version_wins_over_every_source() {
+        let (_directory, lock_path) = project(&[TOOLCHAIN, MANIFEST]);
+
+        let version = ceiling(
+            &lock_path,
+            &["--max-version", "1.60", "--rust-version-source", "auto"],
+        );
+
+        assert_eq!(version.as_deref(), Some("1.60"));
+    }
+
+    #[test]
+    fn auto_tries_toolchain_then_manifest_then_rustc() {
+        let (_directory, lock_path) = project(&[TOOLCHAIN, MANIFEST]);
+
+        let version = ceiling(&lock_path, &["--rust-version-source", "auto"]);
+
+        assert_eq!(version.as_deref(), Some("1.75.0"));
+
+        let (_directory, lock_path) = project(&[MANIFEST]);
+
+        let version = ceiling(&lock_path, &["--rust-version-source", "auto"]);
+
+        assert_eq!(version.as_deref(), Some("1.70"));
+
+        let (_directory, lock_path) = project(&[]);
+
+        let version = ceiling(&lock_path, &["--rust-version-source", "auto"]);
+
+        assert_eq!(version, get_rustc_version());
+    }
+
+    #[test]
+    fn single_source_does_not_fall_back() {
+        let (_directory, lock_path) = project(&[MANIFEST]);
+
+        assert_eq!(
+            ceiling(&lock_path, &["--rust-version-source", "toolchain"]),
+            None
+        );
+
+        let (_directory, lock_path) = project(&[TOOLCHAIN]);
+
+        assert_eq!(
+            ceiling(&lock_path, &["--rust-version-source", "manifest"]),
+            None
+        );
+    }
+
+    #[test]
+    fn no_source_means_no_ceiling() {
+        let (_directory, lock_path) = project(&[TOOLCHAIN, MANIFEST]);
+
+        assert_eq!(ceiling(&lock_path, &[]), None);
+    }
+
+    fn selection(crate_name: &str, versions: &[&str]) -> Selection {
+        let bound = Bound::try_from(&VersionReq::parse(">=0.0.0").unwrap()).unwrap();
+        let versions = versions
+            .iter()
+            .map(|version| ParsedVersion {
+                yanked: false,
+                num: version.parse().unwrap(),
+                rust_version: None,
+                created_at: None,
+            })
+            .collect::<Vec<_>>();
+
+        Selection {
+            crate_name: crate_name.to_string(),
+            range: (versions[0].num.clone(), versions[0].num.clone()),
+            versions,
+            dependents: Vec::new(),
+            allowed: BoundSet::from(bound.clone()),
+            bound,
+            lower_index: 0,
+            upper_index: 0,
+            locked: None,
+        }
+    }
+
+    fn dependency(crate_id: &str, version_req: &str, kind: &str) -> ParsedDependency {
+        ParsedDependency {
+            crate_id: crate_id.to_string(),
+            version_req: version_req.parse().unwrap(),
+            kind: kind.to_string(),
+        }
+    }
+
+    #[test]
+    fn joint_check_only_counts_the_chosen_kinds() {
+        let selections = [
+            selection("helper", &["1.0.0"]),
+            selection("target", &["2.0.0"]),
+        ];
+
+        let dev = dependency("target", "^1", "dev");
+
+        assert!(accepts_a_selected_version(
+            &dev,
+            &selections,
+            DependencyKind::Normal
+        ));
+        assert!(!accepts_a_selected_version(
+            &dev,
+            &selections,
+            DependencyKind::All
+        ));
+        assert!(!accepts_a_selected_version(
+            &dependency("target", "^1", "normal"),
+            &selections,
+            DependencyKind::Normal
+        ));
+    }
+
+    #[test]
+    fn joint_check_looks_at_every_locked_version_of_a_crate() {
+        let selections = [
+            selection("target", &["0.8.5", "0.8.4"]),
+            selection("target", &["0.7.3"]),
+            selection("helper", &["1.0.0"]),
+        ];
+
+        for version_req in ["^0.8", "^0.7"] {
+            assert!(
+                accepts_a_selected_version(
+                    &dependency("target", version_req, "normal"),
+                    &selections,
+                    DependencyKind::Normal
+                ),
+                "{}",
+                version_req
+            );
+        }
+
+        assert!(!accepts_a_selected_version(
+            &dependency("target", "^0.6", "normal"),
+            &selections,
+            DependencyKind::Normal
+        ));
+        assert!(accepts_a_selected_version(
+            &dependency("unchecked", "^9", "normal"),
+            &selections,
+            DependencyKind::Normal
+        ));
+    }
 }
@@ -0,0 +1,227 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+
+use crate::{
+    compat::Count,
+    error::{ConstError, Result},
+    utils::{Ttl, CRATE_NAME},
+};
+
+// The name of the config file kept in a project, beside its Cargo.toml or above it
+const PROJECT_CONFIG_NAME: &str = "cargo-const.toml";
+
+// Defaults read from the config files, flags given on the command line win over them, any
+// of them can be left out
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    pub count: Option<FileCount>,
+    pub include_yanked: Option<bool>,
+    pub cache_ttl: Option<String>,
+    pub color: Option<String>,
+    pub registry: Option<String>,
+}
+
+impl FileConfig {
+    // Each setting from this config, or from `fallback` when this one leaves it out
+    fn or(self, fallback: FileConfig) -> FileConfig {
+        FileConfig {
+            count: self.count.or(fallback.count),
+            include_yanked: self.include_yanked.or(fallback.include_yanked),
+            cache_ttl: self.cache_ttl.or(fallback.cache_ttl),
+            color: self.color.or(fallback.color),
+            registry: self.registry.or(fallback.registry),
+        }
+    }
+}
+
+// Either `count = 10` or `count = "all"`
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum FileCount {
+    Number(usize),
+    Text(String),
+}
+
+impl TryFrom<FileCount> for Count {
+    type Error = ConstError;
+    fn try_from(count: FileCount) -> Result<Self> {
+        match count {
+            FileCount::Number(count) => count.to_string().parse(),
+            FileCount::Text(count) => count.parse(),
+        }
+    }
+}
+
+// $CARGO_CONST_CONFIG if set, otherwise config.toml in the user's config directory
+pub fn get_config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("CARGO_CONST_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+
+    let mut config_dir = dirs::config_dir()?;
+
+    config_dir.push(CRATE_NAME);
+    config_dir.push("config.toml");
+
+    Some(config_dir)
+}
+
+// The nearest cargo-const.toml from the current directory up
+pub fn get_project_config_path() -> Option<PathBuf> {
+    let current_dir = std::env::current_dir().ok()?;
+
+    current_dir
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_NAME))
+        .find(|path| path.is_file())
+}
+
+// The project's config file wins over the user's, setting by setting
+pub fn read_config_file() -> Result<FileConfig> {
+    let user_config = read_config_at(get_config_path())?;
+
+    let project_config = read_config_at(get_project_config_path())?;
+
+    Ok(project_config.or(user_config))
+}
+
+// A missing file is the same as an empty one
+fn read_config_at(path: Option<PathBuf>) -> Result<FileConfig> {
+    let Some(path) = path.filter(|path| path.exists()) else {
+        return Ok(FileConfig::default());
+    };
+
+    let contents = std::fs::read_to_string(&path).map_err(|error| ConstError::OpenFileError {
+        path: path.to_string_lossy().to_string(),
+        error,
+    })?;
+
+    toml::from_str(&contents).map_err(|error| ConstError::ConfigFileParseError {
+        path: path.to_string_lossy().to_string(),
+        error,
+    })
+}
+
+// A setting from the first of the command line, the environment and the config files
+// that gives it, or `default` when none does. Each is only read when the ones before it
+// leave the setting out, so a bad value that would be overridden doesn't fail the run
+pub fn layer<T>(
+    cli: Option<T>,
+    env: impl FnOnce() -> Result<Option<T>>,
+    file: impl FnOnce() -> Result<Option<T>>,
+    default: T,
+) -> Result<T> {
+    if let Some(value) = cli {
+        return Ok(value);
+    }
+
+    if let Some(value) = env()? {
+        return Ok(value);
+    }
+
+    Ok(file()?.unwrap_or(default))
+}
+
+// The defaults from the environment, which win over the config file
+pub fn read_env_count() -> Result<Option<Count>> {
+    std::env::var("CARGO_CONST_COUNT")
+        .ok()
+        .map(|count| count.parse())
+        .transpose()
+}
+
+pub fn read_env_include_yanked() -> Option<bool> {
+    std::env::var("CARGO_CONST_INCLUDE_YANKED")
+        .ok()
+        .map(|value| matches!(value.as_str(), "1" | "true" | "yes"))
+}
+
+pub fn read_env_cache_ttl() -> Result<Option<Ttl>> {
+    std::env::var("CARGO_CONST_CACHE_TTL")
+        .ok()
+        .map(|ttl| ttl.parse())
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unreachable<T>() -> Result<Option<T>> {
+        panic!("a source after the one giving the setting was read")
+    }
+
+    #[test]
+    fn command_line_wins() {
+        let value = layer(Some(1), unreachable, unreachable, 0).unwrap();
+
+        assert_eq!(value, 1);
+    }
+
+    #[test]
+    fn environment_wins_over_file() {
+        let value = layer(None, || Ok(Some(2)), unreachable, 0).unwrap();
+
+        assert_eq!(value, 2);
+    }
+
+    #[test]
+    fn file_then_default() {
+        assert_eq!(layer(None, || Ok(None), || Ok(Some(3)), 0).unwrap(), 3);
+        assert_eq!(layer(None, || Ok(None), || Ok(None), 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn overridden_values_are_not_parsed() {
+        let value = layer(
+            Some(Count::All),
+            || "bad".parse().map(Some),
+            || "bad".parse().map(Some),
+            Count::Count(5),
+        );
+
+        assert!(matches!(value, Ok(Count::All)));
+    }
+
+    #[test]
+    fn project_config_wins_setting_by_setting() {
+        let directory = tempfile::tempdir().unwrap();
+
+        let user = directory.path().join("user.toml");
+        let project = directory.path().join("project.toml");
+
+        std::fs::write(&user, "count = \"all\"\ninclude_yanked = true\n").unwrap();
+        std::fs::write(&project, "count = 10\ncolor = \"never\"\n").unwrap();
+
+        let config = read_config_at(Some(project))
+            .unwrap()
+            .or(read_config_at(Some(user)).unwrap());
+
+        assert!(matches!(
+            config.count.map(Count::try_from),
+            Some(Ok(Count::Count(10)))
+        ));
+        assert_eq!(config.include_yanked, Some(true));
+        assert_eq!(config.color.as_deref(), Some("never"));
+        assert_eq!(config.cache_ttl, None);
+    }
+
+    #[test]
+    fn missing_config_is_empty_and_unknown_keys_fail() {
+        let directory = tempfile::tempdir().unwrap();
+
+        let config = read_config_at(Some(directory.path().join("missing.toml"))).unwrap();
+
+        assert!(config.count.is_none());
+
+        let path = directory.path().join("config.toml");
+
+        std::fs::write(&path, "user_agent = \"me\"\n").unwrap();
+
+        assert!(matches!(
+            read_config_at(Some(path)),
+            Err(ConstError::ConfigFileParseError { .. })
+        ));
+    }
+}
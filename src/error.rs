@@ -1,16 +1,40 @@
+use owo_colors::OwoColorize;
 use semver::{Comparator, VersionReq};
 use thiserror::Error;
 
+use crate::utils::is_colored;
+
 pub type Result<T> = core::result::Result<T, ConstError>;
 
+// The exit codes `ConstError::exit_code` maps errors to, they are documented in the README
+// so once given out a code keeps its meaning
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_UNSATISFIABLE: i32 = 2;
+pub const EXIT_NETWORK: i32 = 3;
+pub const EXIT_IO: i32 = 4;
+pub const EXIT_INVALID_ARGUMENT: i32 = 5;
+
 #[derive(Error, Debug)]
 pub enum ConstError {
     #[error("The version {0} provided for {1} is not valid: {2}")]
     VersionError(Comparator, String, &'static str),
-    #[error("The max rust version {0} is not valid")]
-    InvalidMaxRustVersionError(String),
+    #[error("The max rust version {version} is not valid, {reason}")]
+    InvalidMaxRustVersionError {
+        version: String,
+        reason: RustVersionError,
+    },
+    #[error("No compatible version of {crate_name} matches {requirement}")]
+    NoVersionMatchingError {
+        crate_name: String,
+        requirement: String,
+    },
     #[error("No satisfiable version of crate has a max version of {0}")]
     UnsatisfiableMaxRustVersionError(String),
+    #[error("No satisfiable version of {crate_name} is at least as stable as {stability}")]
+    UnsatisfiableStabilityError {
+        crate_name: String,
+        stability: String,
+    },
     #[error("The version requirement for {crate_name}{crate_version} is empty")]
     EmptyVersionReqError {
         crate_name: String,
@@ -18,30 +42,70 @@ pub enum ConstError {
     },
     #[error("Could not open file at {path}: {error}")]
     OpenFileError { path: String, error: std::io::Error },
+    #[error("Could not write file at {path}: {error}")]
+    WriteFileError { path: String, error: std::io::Error },
+    #[error("{}", display_crate_not_found_error(crate_name, crate_version))]
+    CrateNotFoundError {
+        crate_name: String,
+        crate_version: Option<String>,
+    },
+    #[error("{}", display_offline_cache_miss_error(crate_name, crate_version))]
+    OfflineCacheMissError {
+        crate_name: String,
+        crate_version: Option<String>,
+    },
+    #[error("crates.io could not be reached or failed to respond, try again later: {0}")]
+    TransientFetchError(crates_io_api::Error),
+    #[error("The request to {registry} timed out, try again later")]
+    RequestTimeoutError { registry: String },
     #[error("Error while fetching crate dependencies: {0}")]
     CrateDependencyFetchError(crates_io_api::Error),
     #[error("Error while fetching crate info: {0}")]
     CrateInfoFetchError(crates_io_api::Error),
+    #[error("No registry named {registry} found in the environment or the cargo config")]
+    RegistryNotFoundError { registry: String },
+    #[error("The index of registry {registry} at {index} is not a sparse index, the only kind supported")]
+    UnsupportedRegistryIndexError { registry: String, index: String },
+    #[error("Registry {registry} could not be reached or failed to respond: {error}")]
+    RegistryUnreachableError {
+        registry: String,
+        error: reqwest::Error,
+    },
+    #[error("Could not parse the index entry of {crate_name} from registry {registry}: {error}")]
+    RegistryIndexParseError {
+        registry: String,
+        crate_name: String,
+        error: serde_json::Error,
+    },
+    #[error("Could not parse cargo config at {path}: {error}")]
+    CargoConfigParseError {
+        path: String,
+        error: toml::de::Error,
+    },
     #[error("Could not parse version requirement: {0}")]
     VersionReqParseError(semver::Error),
     #[error("Could not parse version: {0}")]
     VersionParseError(semver::Error),
-    #[error(
-        "Invalid file contents, could not deserialize {type_name} from file at {path}: {error}"
-    )]
+    #[error("Could not read {type_name} from the cache entry at {path}: {error}")]
     DeserializeFromFileError {
         type_name: &'static str,
         path: String,
         error: serde_cbor::Error,
     },
+    #[error("The cache entry at {path} is damaged or was cut short")]
+    CorruptCacheEntryError { path: String },
     #[error("Could not deserialize {type_name} into file at {path}: {error}")]
     SerializeToFileError {
         type_name: &'static str,
         path: String,
         error: serde_cbor::Error,
     },
+    #[error("Stopped after reaching the limit of {0} requests to crates.io")]
+    NetworkLimitReachedError(usize),
     #[error("Could not get data directory")]
     DataDirectoryError,
+    #[error("Could not clear the cache at {path}: {error}")]
+    ClearCacheError { path: String, error: std::io::Error },
     #[error("Could not create the {path} directory: {error}")]
     CreateParentDirectoryError { path: String, error: std::io::Error },
     #[error(
@@ -53,6 +117,8 @@ pub enum ConstError {
         crate_name: String,
         crate_version: String,
     },
+    #[error("The bounds {first} and {second} don't overlap")]
+    DisjointBoundsError { first: String, second: String },
     #[error("The crate {0} does not match any dependencies")]
     NoMatchingDependentError(String),
     #[error(
@@ -81,11 +147,81 @@ pub enum ConstError {
         crate_name: String,
         dependent: ((String, String), VersionReq),
     },
+    #[error("Found neither a lockfile at {lock_path} nor a manifest at {manifest_path}")]
+    NoLockfileOrManifestError {
+        lock_path: String,
+        manifest_path: String,
+    },
     #[error("Could not open lock file at {path}: {error}")]
     CouldNotLoadLockFileError {
         path: String,
         error: cargo_lock::Error,
     },
+    #[error("Could not read the lock file from stdin: {0}")]
+    ReadStdinError(std::io::Error),
+    #[error("Could not parse the lock file read from stdin: {0}")]
+    StdinLockFileParseError(cargo_lock::Error),
+    #[error("Could not parse lock file at {path}: {error}")]
+    LockFileParseError {
+        path: String,
+        error: toml::de::Error,
+    },
+    #[error(
+        "{}",
+        display_at_least_above_bound_error(crate_name, crate_version, dependent)
+    )]
+    AtLeastAboveBoundError {
+        crate_name: String,
+        crate_version: String,
+        dependent: ((String, String), VersionReq),
+    },
+    #[error("No version of {crate_name} within the bound is left after filtering to pick a minimum from")]
+    NoMinimumVersionError { crate_name: String },
+    #[error(
+        "The crate {crate_name} comes from git in the lockfile, pass --treat-as-registry to resolve it against crates.io anyway"
+    )]
+    GitSourceError { crate_name: String },
+    #[error("{}", display_not_in_dependency_graph_error(crate_name, suggestions))]
+    NotInDependencyGraphError {
+        crate_name: String,
+        suggestions: Vec<String>,
+    },
+    #[error("The crate {crate_name} is not in the lockfile at {path}")]
+    NotInLockfileError { crate_name: String, path: String },
+    #[error("Could not parse config file at {path}: {error}")]
+    ConfigFileParseError {
+        path: String,
+        error: toml::de::Error,
+    },
+    #[error("Could not parse manifest at {path}: {error}")]
+    ManifestParseError {
+        path: String,
+        error: toml_edit::TomlError,
+    },
+    #[error("The manifest at {path} has no versioned dependency on {crate_name}")]
+    DependencyNotInManifestError { crate_name: String, path: String },
+    #[error("{}", display_pin_out_of_date_error(pins, path))]
+    PinOutOfDateError {
+        pins: Vec<(String, String)>,
+        path: String,
+    },
+    #[error("The manifest at {path} has no package name")]
+    PackageNameNotInManifestError { path: String },
+    #[error("{}", display_unknown_workspace_member_error(package, members))]
+    UnknownWorkspaceMemberError {
+        package: String,
+        members: Vec<String>,
+    },
+    #[error("The lockfile has {crate_name} {current} but {newest} is compatible and newer")]
+    NewerVersionExistsError {
+        crate_name: String,
+        current: String,
+        newest: String,
+    },
+    #[error("Could not get cargo metadata: {0}")]
+    CargoMetadataError(String),
+    #[error("Could not parse cargo metadata: {0}")]
+    MetadataParseError(serde_json::Error),
     #[error(
         "The crate {crate_name} has a prerelease version {crate_version} which is not supported"
     )]
@@ -93,8 +229,17 @@ pub enum ConstError {
         crate_name: String,
         crate_version: String,
     },
-    #[error("Only yanked version of crate {crate_name} satisfies the dependents requirements")]
-    OnlyYankedVersionExistsError { crate_name: String },
+    #[error("{}", display_only_yanked_version_exists_error(crate_name, versions))]
+    OnlyYankedVersionExistsError {
+        crate_name: String,
+        versions: Vec<String>,
+    },
+    #[error(
+        "Every version of crate {crate_name} within the bound is excluded by a dependent's requirement"
+    )]
+    ExcludedByRequirementsError { crate_name: String },
+    #[error("No compatible version was found for {}", .crate_names.join(", "))]
+    NoCompatibleVersionError { crate_names: Vec<String> },
     #[error(
         "The crate {crate_name} with version {crate_version} has the dependency {dependency} in the lockfile but crates.io says otherwise"
     )]
@@ -103,8 +248,199 @@ pub enum ConstError {
         crate_version: String,
         dependency: String,
     },
-    #[error("Expected \"all\" or a number, got {argument}")]
+    #[error("{}", display_joint_conflict_error(conflicts))]
+    JointConflictError {
+        conflicts: Vec<((String, String), VersionReq, String)>,
+    },
+    #[error("{}", display_invalid_count_argument(argument))]
     InvalidCountArgument { argument: String },
+    #[error("Expected \"text\", \"json\", \"plain\", \"junit\" or \"toml\", got {argument}")]
+    InvalidFormatArgument { argument: String },
+    #[error(
+        "Expected FORMAT=PATH with FORMAT one of \"text\", \"json\", \"plain\" or \"toml\", got {argument}"
+    )]
+    InvalidEmitArgument { argument: String },
+    #[error("Expected \"auto\", \"toolchain\", \"manifest\" or \"rustc\", got {argument}")]
+    InvalidRustVersionSourceArgument { argument: String },
+    #[error("Expected \"alpha\", \"beta\", \"rc\" or \"stable\", got {argument}")]
+    InvalidStabilityArgument { argument: String },
+    #[error("Expected \"auto\", \"always\" or \"never\", got {argument}")]
+    InvalidColorArgument { argument: String },
+    #[error("Expected \"normal\", \"build\", \"dev\" or \"all\", got {argument}")]
+    InvalidKindArgument { argument: String },
+    #[error("Expected \"desc\", \"asc\" or \"msrv\", got {argument}")]
+    InvalidSortArgument { argument: String },
+    #[error("Expected a duration such as 30m, 1h or 7d, got {argument}")]
+    InvalidCacheTtlArgument { argument: String },
+    #[error("--quiet and --verbose can't be used together")]
+    QuietAndVerboseError,
+    #[error("--format junit can't be used together with {option}")]
+    JunitAndOptionError { option: String },
+    #[error("The request delay can be at most {max}ms, got {delay}ms")]
+    InvalidRequestDelayArgument { delay: u64, max: u64 },
+    #[error("The timeout must be at least a second")]
+    InvalidTimeoutArgument,
+    #[error("--timeout only applies to a registry given with --registry, not to crates.io")]
+    TimeoutWithoutRegistryError,
+    #[error("Expected a date as YYYY-MM-DD, got {argument}")]
+    InvalidDateArgument { argument: String },
+    #[error("Expected a lockfile format version from 1 to 4, got {argument}")]
+    InvalidLockVersionArgument { argument: u32 },
+    #[error("The lockfile at {path} is in format version {found}, expected version {expected}")]
+    LockVersionMismatchError {
+        path: String,
+        expected: u32,
+        found: u32,
+    },
+    #[error("{failed} of the {total} crates could not be resolved")]
+    DependenciesFailedError { failed: usize, total: usize },
+    #[error("Could not serialize output: {0}")]
+    SerializeOutputError(serde_json::Error),
+    #[error("Could not serialize output: {0}")]
+    SerializeTomlOutputError(toml::ser::Error),
+}
+
+// Why a rust version such as 1.70.0 couldn't be read
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RustVersionError {
+    #[error("it is empty")]
+    Empty,
+    #[error("release channels like {0} aren't supported, only stable versions")]
+    ChannelSuffix(String),
+    #[error("it has {0} parts where at most 3 are expected")]
+    TooManyParts(usize),
+    #[error("{0} is not a number")]
+    InvalidPart(String),
+}
+
+impl ConstError {
+    // Whether asking crates.io or the registry again might succeed
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ConstError::TransientFetchError(_) | ConstError::RequestTimeoutError { .. } => true,
+            ConstError::RegistryUnreachableError { error, .. } => {
+                error.is_timeout()
+                    || error.is_connect()
+                    || error
+                        .status()
+                        .is_some_and(|status| status.is_server_error() || status.as_u16().eq(&429))
+            }
+            _ => false,
+        }
+    }
+
+    // Whether the error means no version fits, as opposed to the check itself failing
+    pub fn is_unsatisfiable(&self) -> bool {
+        matches!(
+            self,
+            ConstError::UnsatisfiableSingleDependentError { .. }
+                | ConstError::UnsatisfiableBoundDependentsError { .. }
+                | ConstError::UnsatisfiableMultipleDependentsError { .. }
+                | ConstError::UnsatisfiableMaxRustVersionError(_)
+                | ConstError::UnsatisfiableStabilityError { .. }
+                | ConstError::OnlyYankedVersionExistsError { .. }
+                | ConstError::ExcludedByRequirementsError { .. }
+                | ConstError::NoCompatibleVersionError { .. }
+                | ConstError::AtLeastAboveBoundError { .. }
+                | ConstError::NoMinimumVersionError { .. }
+                | ConstError::NoVersionMatchingError { .. }
+        )
+    }
+
+    // The code to exit with, by what kind of failure this is, anything not listed gets
+    // EXIT_FAILURE
+    pub fn exit_code(&self) -> i32 {
+        if self.is_unsatisfiable() {
+            return EXIT_UNSATISFIABLE;
+        }
+
+        match self {
+            ConstError::TransientFetchError(_)
+            | ConstError::RequestTimeoutError { .. }
+            | ConstError::CrateDependencyFetchError(_)
+            | ConstError::CrateInfoFetchError(_)
+            | ConstError::RegistryUnreachableError { .. }
+            | ConstError::RegistryIndexParseError { .. }
+            | ConstError::NetworkLimitReachedError(_)
+            | ConstError::OfflineCacheMissError { .. } => EXIT_NETWORK,
+            ConstError::OpenFileError { .. }
+            | ConstError::WriteFileError { .. }
+            | ConstError::ReadStdinError(_)
+            | ConstError::DeserializeFromFileError { .. }
+            | ConstError::CorruptCacheEntryError { .. }
+            | ConstError::SerializeToFileError { .. }
+            | ConstError::DataDirectoryError
+            | ConstError::ClearCacheError { .. }
+            | ConstError::CreateParentDirectoryError { .. } => EXIT_IO,
+            ConstError::InvalidMaxRustVersionError { .. }
+            | ConstError::InvalidCountArgument { .. }
+            | ConstError::InvalidFormatArgument { .. }
+            | ConstError::InvalidEmitArgument { .. }
+            | ConstError::InvalidRustVersionSourceArgument { .. }
+            | ConstError::InvalidStabilityArgument { .. }
+            | ConstError::InvalidColorArgument { .. }
+            | ConstError::InvalidKindArgument { .. }
+            | ConstError::InvalidSortArgument { .. }
+            | ConstError::InvalidCacheTtlArgument { .. }
+            | ConstError::InvalidRequestDelayArgument { .. }
+            | ConstError::InvalidTimeoutArgument
+            | ConstError::TimeoutWithoutRegistryError
+            | ConstError::InvalidDateArgument { .. }
+            | ConstError::InvalidLockVersionArgument { .. }
+            | ConstError::QuietAndVerboseError
+            | ConstError::JunitAndOptionError { .. }
+            | ConstError::RegistryNotFoundError { .. }
+            | ConstError::UnsupportedRegistryIndexError { .. }
+            | ConstError::UnknownWorkspaceMemberError { .. } => EXIT_INVALID_ARGUMENT,
+            _ => EXIT_FAILURE,
+        }
+    }
+
+    // The dependents whose requirements conflict, for the errors that name them
+    pub fn conflicting_dependents(&self) -> Vec<(String, String)> {
+        match self {
+            ConstError::UnsatisfiableSingleDependentError { dependent, .. } => {
+                vec![dependent.0.clone()]
+            }
+            ConstError::UnsatisfiableBoundDependentsError { lower, upper, .. } => {
+                vec![lower.0.clone(), upper.0.clone()]
+            }
+            ConstError::UnsatisfiableMultipleDependentsError {
+                dependent,
+                dependents,
+                ..
+            } => std::iter::once(dependent)
+                .chain(dependents)
+                .map(|dependent| dependent.0.clone())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn display_crate_not_found_error(crate_name: &String, crate_version: &Option<String>) -> String {
+    match crate_version {
+        Some(crate_version) => format!(
+            "Version {} of the crate {} does not exist on crates.io",
+            crate_version, crate_name
+        ),
+        None => format!("The crate {} does not exist on crates.io", crate_name),
+    }
+}
+
+fn display_offline_cache_miss_error(crate_name: &String, crate_version: &Option<String>) -> String {
+    match crate_version {
+        Some(crate_version) => format!(
+            "The dependencies of {} {} are not cached and can't be fetched offline, run once \
+            without --offline to cache them",
+            crate_name, crate_version
+        ),
+        None => format!(
+            "The versions of {} are not cached and can't be fetched offline, run once without \
+            --offline to cache them",
+            crate_name
+        ),
+    }
 }
 
 fn display_non_overlapping_bounds_error(
@@ -113,7 +449,7 @@ fn display_non_overlapping_bounds_error(
     crate_version: &String,
 ) -> String {
     format!(
-        "The dependent crate {}{} provided has invalid version requirements {}",
+        "The dependent crate {} {} provided has invalid version requirements {}",
         crate_name, crate_version, version_req
     )
 }
@@ -123,9 +459,9 @@ fn display_unsatisfiable_single_dependent_error(
     dependent: &((String, String), VersionReq),
 ) -> String {
     format!(
-        "The dependent crate {}{} provided with version requirements for {} of {} \n\
-        does not match any vesions of {}",
-        dependent.0 .0, dependent.0 .1, crate_name, dependent.0 .1, crate_name
+        "The dependent crate {} {} provided with version requirements for {} of {}\n\
+        does not match any versions of {}",
+        dependent.0 .0, dependent.0 .1, crate_name, dependent.1, crate_name
     )
 }
 
@@ -136,8 +472,8 @@ fn display_unsatisfiable_bound_dependent_error(
 ) -> String {
     format!(
         "\
-        A version of the {} crate could not be selected due to {}{} with requirement {}\n\
-        being incompatitible with {}{} with requirement {}, no version of {} satisfies those\
+        A version of the {} crate could not be selected due to {} {} with requirement {}\n\
+        being incompatible with {} {} with requirement {}, no version of {} satisfies those \
         requirements",
         crate_name, lower.0 .0, lower.0 .1, lower.1, upper.0 .0, upper.0 .1, upper.1, crate_name
     )
@@ -153,7 +489,7 @@ fn display_unsatisfiable_multiple_dependent_error(
     for dependency in dependencies {
         dependencies_as_string.push_str(
             format!(
-                "crate: {}{} with dependency requirement: {}\n",
+                "crate: {} {} with dependency requirement: {}\n",
                 dependency.0 .0, dependency.0 .1, dependency.1
             )
             .as_str(),
@@ -162,8 +498,8 @@ fn display_unsatisfiable_multiple_dependent_error(
 
     format!(
         "\
-        A version of the {} crate could not be selected due to {}{} with requirement {}\n\
-        being incompatitible with:-\n\
+        A version of the {} crate could not be selected due to {} {} with requirement {}\n\
+        being incompatible with:-\n\
         {}\
         no version of {} matches those requirements",
         crate_name,
@@ -175,7 +511,245 @@ fn display_unsatisfiable_multiple_dependent_error(
     )
 }
 
+fn display_joint_conflict_error(conflicts: &Vec<((String, String), VersionReq, String)>) -> String {
+    let mut conflicts_as_string = String::new();
+
+    for conflict in conflicts {
+        conflicts_as_string.push_str(
+            format!(
+                "crate: {} {} requires {} {} which matches none of the versions found for it\n",
+                conflict.0 .0, conflict.0 .1, conflict.2, conflict.1
+            )
+            .as_str(),
+        );
+    }
+
+    format!(
+        "\
+        The versions found for each crate are not compatible with each other:-\n\
+        {}",
+        conflicts_as_string
+    )
+}
+
+fn display_at_least_above_bound_error(
+    crate_name: &String,
+    crate_version: &String,
+    dependent: &((String, String), VersionReq),
+) -> String {
+    format!(
+        "No version of {} from {} up can be used, the dependent crate {} {} requires {} {}, \
+        it has to be upgraded first",
+        crate_name, crate_version, dependent.0 .0, dependent.0 .1, crate_name, dependent.1
+    )
+}
+
+fn display_invalid_count_argument(argument: &str) -> String {
+    format!(
+        "\
+        {} is not a valid count, expected one of:-\n  \
+        all    list every compatible version\n  \
+        <N>    list at most N versions, from 1 up, e.g. --count 10",
+        match is_colored() {
+            true => format!("\"{}\"", argument).bold().to_string(),
+            false => format!("\"{}\"", argument),
+        }
+    )
+}
+
+fn display_only_yanked_version_exists_error(crate_name: &String, versions: &[String]) -> String {
+    format!(
+        "Only yanked versions of crate {} satisfy the dependents requirements: {}\n{}",
+        crate_name,
+        versions.join(", "),
+        "Pass --include-yanked to use them"
+    )
+}
+
+fn display_pin_out_of_date_error(pins: &[(String, String)], path: &String) -> String {
+    let pins = pins
+        .iter()
+        .map(|(crate_name, crate_version)| format!("{} to {}", crate_name, crate_version))
+        .collect::<Vec<_>>();
+
+    format!("The manifest at {} does not pin {}", path, pins.join(", "))
+}
+
+fn display_not_in_dependency_graph_error(crate_name: &String, suggestions: &[String]) -> String {
+    let message = format!(
+        "The crate {} is not part of this project's dependency graph",
+        crate_name
+    );
+
+    match suggestions {
+        [] => message,
+        [suggestion] => format!("{}\nDid you mean {}?", message, suggestion),
+        [rest @ .., last] => format!("{}\nDid you mean {} or {}?", message, rest.join(", "), last),
+    }
+}
+
+fn display_unknown_workspace_member_error(package: &String, members: &[String]) -> String {
+    format!(
+        "The package {} is not a member of the workspace, the members are: {}",
+        package,
+        members.join(", ")
+    )
+}
+
 pub const NO_VERSION_BELOW: &str = "No version below";
 pub const NO_VERSION_ABOVE: &str = "No version above";
 pub const UNSUPPORTED_SEMVER_OPERATOR: &str = "Unsupported semver operator";
 pub const PRELEASE_NOT_SUPPORTED: &str = "Prerelease versions are not supported";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dependent(name: &str, version: &str, requirement: &str) -> ((String, String), VersionReq) {
+        (
+            (name.to_string(), version.to_string()),
+            requirement.parse().unwrap(),
+        )
+    }
+
+    fn crate_name() -> String {
+        "helper".to_string()
+    }
+
+    #[test]
+    fn unsatisfiable_messages() {
+        let errors = [
+            (
+                "single_dependent",
+                ConstError::UnsatisfiableSingleDependentError {
+                    crate_name: crate_name(),
+                    dependent: dependent("app-a", "1.0.0", "^0.9"),
+                },
+            ),
+            (
+                "bound_dependents",
+                ConstError::UnsatisfiableBoundDependentsError {
+                    crate_name: crate_name(),
+                    lower: dependent("app-a", "1.0.0", ">=0.5"),
+                    upper: dependent("app-b", "2.1.0", "<0.4"),
+                },
+            ),
+            (
+                "multiple_dependents",
+                ConstError::UnsatisfiableMultipleDependentsError {
+                    crate_name: crate_name(),
+                    dependent: dependent("app-a", "1.0.0", "^0.5"),
+                    dependents: vec![
+                        dependent("app-b", "2.1.0", "^0.4"),
+                        dependent("app-c", "0.3.0", "=0.3.1"),
+                    ],
+                },
+            ),
+            (
+                "max_rust_version",
+                ConstError::UnsatisfiableMaxRustVersionError("1.60".to_string()),
+            ),
+            (
+                "stability",
+                ConstError::UnsatisfiableStabilityError {
+                    crate_name: crate_name(),
+                    stability: "beta".to_string(),
+                },
+            ),
+            (
+                "only_yanked",
+                ConstError::OnlyYankedVersionExistsError {
+                    crate_name: crate_name(),
+                    versions: vec!["0.4.2".to_string(), "0.4.1".to_string()],
+                },
+            ),
+            (
+                "excluded_by_requirements",
+                ConstError::ExcludedByRequirementsError {
+                    crate_name: crate_name(),
+                },
+            ),
+            (
+                "at_least_above_bound",
+                ConstError::AtLeastAboveBoundError {
+                    crate_name: crate_name(),
+                    crate_version: "0.5.0".to_string(),
+                    dependent: dependent("app-a", "1.0.0", "^0.4"),
+                },
+            ),
+            (
+                "no_minimum",
+                ConstError::NoMinimumVersionError {
+                    crate_name: crate_name(),
+                },
+            ),
+            (
+                "no_version_matching",
+                ConstError::NoVersionMatchingError {
+                    crate_name: crate_name(),
+                    requirement: "^1".to_string(),
+                },
+            ),
+            (
+                "no_compatible_version",
+                ConstError::NoCompatibleVersionError {
+                    crate_names: vec![crate_name(), "target".to_string()],
+                },
+            ),
+        ];
+
+        for (name, error) in errors {
+            assert!(error.is_unsatisfiable(), "{}", name);
+
+            insta::assert_snapshot!(name, error.to_string());
+        }
+    }
+
+    #[test]
+    fn other_messages() {
+        let errors = [
+            (
+                "joint_conflict",
+                ConstError::JointConflictError {
+                    conflicts: vec![(
+                        ("helper".to_string(), "0.4.2".to_string()),
+                        "^2".parse().unwrap(),
+                        "target".to_string(),
+                    )],
+                },
+            ),
+            (
+                "not_in_dependency_graph",
+                ConstError::NotInDependencyGraphError {
+                    crate_name: "serd".to_string(),
+                    suggestions: vec![
+                        "serde".to_string(),
+                        "serde_json".to_string(),
+                        "sera".to_string(),
+                    ],
+                },
+            ),
+            (
+                "pin_out_of_date",
+                ConstError::PinOutOfDateError {
+                    pins: vec![
+                        ("helper".to_string(), "0.4.2".to_string()),
+                        ("target".to_string(), "1.3.1".to_string()),
+                    ],
+                    path: "Cargo.toml".to_string(),
+                },
+            ),
+            (
+                "dependencies_failed",
+                ConstError::DependenciesFailedError {
+                    failed: 1,
+                    total: 3,
+                },
+            ),
+        ];
+
+        for (name, error) in errors {
+            insta::assert_snapshot!(name, error.to_string());
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use semver::{Version, VersionReq};
+use std::ops::{Add, Mul, Sub};
+
+use crate::{
+    bound::{
+        collect_constraints, suggest_resolution, trace_merge, Bound, DependentConstraint,
+        Resolution,
+    },
+    error::Result,
+    get_config,
+    provider::Provider,
+    utils::eprint_header_and_items,
+};
+
+// Width of the row names in front of the bars
+const ROW_NAME_WIDTH: usize = 12;
+
+// Label of the column open upper ends are drawn to
+const OPEN_LABEL: &str = "inf";
+
+// Print how the bound narrows as each dependent's bound is merged in, drawn as bars over
+// an axis made up of every endpoint involved
+pub fn explain_merge(
+    client: &Provider,
+    crate_to_find: &str,
+    dependents: Vec<(String, String)>,
+) -> Result<()> {
+    let dependent_constraints = collect_constraints(client, crate_to_find, dependents)?;
+
+    let axis = Axis::new(&dependent_constraints);
+
+    eprintln!("{}{}", " ".repeat(ROW_NAME_WIDTH), axis.labels().trim_end());
+
+    let mut previous: Option<&Bound> = None;
+
+    let steps = trace_merge(&dependent_constraints);
+
+    for step in &steps {
+        let ((name, version), bound, version_req) = step.constraint;
+
+        eprintln!();
+        eprintln!("{} {} requires {}", name, version, version_req);
+
+        if let Some(previous) = previous {
+            print_row("before", &axis.bar(previous));
+        }
+
+        print_row("requires", &axis.bar(bound));
+
+        match &step.merged {
+            Some(merged) => print_row("after", &axis.bar(merged)),
+            None => print_row(
+                "after",
+                "no overlap, no version can satisfy every dependent",
+            ),
+        }
+
+        previous = step.merged.as_ref();
+    }
+
+    Ok(())
+}
+
+// Print the most dependents that agree on a range of the crate, and which of the others
+// would need a newer or older version of themselves to fit in it
+pub fn explain_resolution(
+    client: &Provider,
+    crate_to_find: &str,
+    dependents: Vec<(String, String)>,
+) -> Result<()> {
+    let dependent_constraints = collect_constraints(client, crate_to_find, dependents)?;
+
+    let total = dependent_constraints.len();
+
+    let versions = client
+        .get_versions(crate_to_find)?
+        .versions
+        .into_iter()
+        .filter(|version| get_config().include_yanked || !version.yanked)
+        .map(|version| version.num)
+        .collect::<Vec<_>>();
+
+    let Resolution {
+        merged_bound,
+        left_out,
+    } = suggest_resolution(crate_to_find, dependent_constraints, &versions)?;
+
+    let requirement = VersionReq::from(&merged_bound.bound);
+
+    // The dependents overlap but no version was published in between
+    if left_out.is_empty() {
+        eprintln!(
+            "Every dependent agrees on {} {}, but no version of it falls in that range",
+            crate_to_find, requirement
+        );
+        eprintln!();
+
+        return Ok(());
+    }
+
+    let agreeing = merged_bound
+        .dependents
+        .iter()
+        .map(|((name, version), version_req)| {
+            format!("{} {} requires {}", name, version, version_req)
+        });
+
+    eprint_header_and_items(
+        &format!(
+            "{} of {} dependents agree on {} {}",
+            total - left_out.len(),
+            total,
+            crate_to_find,
+            requirement
+        ),
+        agreeing,
+    );
+    eprintln!();
+
+    let suggestions = left_out
+        .iter()
+        .map(|((name, version), version_req, below)| {
+            format!(
+                "{} {} requires {}, {} version of {} is needed",
+                name,
+                version,
+                version_req,
+                if *below { "a newer" } else { "an older" },
+                name
+            )
+        });
+
+    eprint_header_and_items("To fit the others in that range", suggestions);
+    eprintln!();
+
+    Ok(())
+}
+
+fn print_row(name: &str, content: &str) {
+    eprintln!("  {:<width$}{}", name, content, width = ROW_NAME_WIDTH - 2);
+}
+
+// Every distinct endpoint in order, each given a column of the same width, with one more
+// column at the end for open upper ends if any bound has one
+struct Axis {
+    versions: Vec<Version>,
+    open: bool,
+    width: usize,
+}
+
+impl Axis {
+    fn new(dependent_constraints: &[DependentConstraint]) -> Axis {
+        let mut versions = dependent_constraints
+            .iter()
+            .flat_map(|(_, bound, _)| {
+                [
+                    Some(bound.lower.version.clone()),
+                    bound.upper.as_range().map(|(version, _)| version.clone()),
+                ]
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+
+        versions.sort();
+        versions.dedup();
+
+        let open = dependent_constraints
+            .iter()
+            .any(|(_, bound, _)| bound.upper.as_range().is_none());
+
+        let width = versions
+            .iter()
+            .map(|version| version.to_string().chars().count())
+            .chain(open.then_some(OPEN_LABEL.len()))
+            .max()
+            .unwrap_or(0)
+            .add(2);
+
+        Axis {
+            versions,
+            open,
+            width,
+        }
+    }
+
+    fn columns(&self) -> usize {
+        self.versions.len().add(usize::from(self.open))
+    }
+
+    fn position(&self, version: &Version) -> usize {
+        self.versions
+            .binary_search(version)
+            .unwrap_or_else(|position| position)
+            .mul(self.width)
+    }
+
+    fn labels(&self) -> String {
+        self.versions
+            .iter()
+            .map(Version::to_string)
+            .chain(self.open.then(|| OPEN_LABEL.to_string()))
+            .map(|label| format!("{:<width$}", label, width = self.width))
+            .collect()
+    }
+
+    fn bar(&self, bound: &Bound) -> String {
+        let length = self.columns().saturating_sub(1).mul(self.width).add(1);
+
+        let mut bar = vec!['-'; length];
+
+        let lower = self.position(&bound.lower.version);
+
+        let (upper, upper_char) = match bound.upper.as_range() {
+            Some((version, inclusive)) => {
+                (self.position(version), if inclusive { ']' } else { ')' })
+            }
+            None => (length.sub(1), '>'),
+        };
+
+        if lower.eq(&upper) {
+            bar[lower] = '|';
+        } else {
+            bar[lower..upper].fill('=');
+            bar[lower] = if bound.lower.inclusive { '[' } else { '(' };
+            bar[upper] = upper_char;
+        }
+
+        bar.into_iter().collect()
+    }
+}
@@ -0,0 +1,171 @@
+// ConstError carries dependent requirements for reporting, which makes it large
+#![allow(clippy::result_large_err)]
+
+use cargo_lock::Lockfile;
+use std::sync::OnceLock;
+
+use crate::{
+    bound::{check_in_lockfile, find_dependents, find_packed_bound},
+    compat::{Count, DependencyKind},
+    error::{ConstError, Result},
+    provider::{ParsedVersion, Provider},
+    registry::Registry,
+    utils::{get_rust_version, Date, DEFAULT_TIMEOUT, MAX_CACHE_AGE},
+};
+
+pub mod bound;
+pub mod cache;
+pub mod compat;
+pub mod config;
+pub mod error;
+pub mod explain;
+pub mod locked;
+pub mod lockfile;
+pub mod manifest;
+pub mod metadata;
+pub mod provider;
+pub mod registry;
+pub mod render;
+pub mod report;
+pub mod utils;
+pub mod versions;
+pub mod why;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+// The settings everything shares, the binary sets them from its arguments and anything
+// else embedding the crate gets the defaults unless it sets them first
+pub fn get_config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+// Hands the config back if one was already set or read
+pub fn set_config(config: Config) -> std::result::Result<(), Config> {
+    CONFIG.set(config)
+}
+
+#[derive(Debug)]
+pub struct Config {
+    pub verbosity: u8,
+    pub quiet: bool,
+    // Off with --quiet, but not with the machine-readable formats that turn `quiet` on, as
+    // their renderers print the warnings to stderr in place of the logger
+    pub warnings: bool,
+    pub progress: bool,
+    pub colored: bool,
+    pub lenient: bool,
+    pub limit_network: Option<usize>,
+    pub retries: u32,
+    pub request_delay: u64,
+    // Seconds before a request to a registry is given up on
+    pub timeout: u64,
+    pub as_of: Option<Date>,
+    pub count: Count,
+    pub include_yanked: bool,
+    // Leave out the prereleases cargo would, see `--pre`
+    pub pre: bool,
+    pub kind: DependencyKind,
+    pub concurrency_safe_cache_dir: bool,
+    pub offline: bool,
+    pub no_cache: bool,
+    pub cache_ttl: u64,
+    // Seconds a cached entry can be before using it is warned about
+    pub max_age_warning: Option<u64>,
+    pub registry: Option<Registry>,
+}
+
+// The same as the binary with no flags, except that nothing but errors is printed as
+// the caller decides what to show
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            verbosity: 0,
+            quiet: true,
+            warnings: false,
+            progress: false,
+            colored: false,
+            lenient: false,
+            limit_network: None,
+            retries: 3,
+            request_delay: 100,
+            timeout: DEFAULT_TIMEOUT,
+            as_of: None,
+            count: Count::Count(5),
+            include_yanked: false,
+            pre: false,
+            kind: DependencyKind::Normal,
+            concurrency_safe_cache_dir: false,
+            offline: false,
+            no_cache: false,
+            cache_ttl: MAX_CACHE_AGE,
+            max_age_warning: None,
+            registry: None,
+        }
+    }
+}
+
+// What to leave out of the versions `find_compatible_versions` returns
+#[derive(Debug, Clone)]
+pub struct CompatOptions {
+    pub include_yanked: bool,
+    // Leave out versions needing a newer rust than this, such as "1.70"
+    pub max_rust_version: Option<String>,
+    pub count: Count,
+}
+
+impl Default for CompatOptions {
+    fn default() -> Self {
+        CompatOptions {
+            include_yanked: false,
+            max_rust_version: None,
+            count: Count::Count(5),
+        }
+    }
+}
+
+// Find the versions of `crate_name` that every dependent in the lockfile accepts, newest
+// first, as `cargo const compat` would list them but without printing anything
+pub fn find_compatible_versions(
+    lock: &Lockfile,
+    crate_name: &str,
+    options: &CompatOptions,
+) -> Result<Vec<ParsedVersion>> {
+    let max_rust_version = match &options.max_rust_version {
+        Some(version) => Some(get_rust_version(version).map_err(|reason| {
+            ConstError::InvalidMaxRustVersionError {
+                version: version.clone(),
+                reason,
+            }
+        })?),
+        None => None,
+    };
+
+    check_in_lockfile(crate_name, lock)?;
+
+    let provider = Provider::new();
+
+    let packed_bound = find_packed_bound(&provider, crate_name, find_dependents(crate_name, lock))?;
+
+    let versions = packed_bound
+        .in_range()
+        .iter()
+        .rev()
+        .filter(|version| options.include_yanked || !version.yanked)
+        .filter(|version| {
+            // Versions that don't say which rust they need are kept
+            let rust_version = version
+                .rust_version
+                .as_deref()
+                .and_then(|rust_version| get_rust_version(rust_version).ok());
+
+            match (rust_version, max_rust_version) {
+                (Some(rust_version), Some(max_rust_version)) => rust_version.le(&max_rust_version),
+                _ => true,
+            }
+        })
+        .take(options.count.limit())
+        .cloned()
+        .collect();
+
+    Ok(versions)
+}
@@ -0,0 +1,46 @@
+use cargo_lock::Lockfile;
+use clap::Parser;
+
+use crate::error::{ConstError, Result};
+
+/// Print the versions of a crate recorded in the lockfile, without contacting crates.io
+#[derive(Parser)]
+pub struct Locked {
+    /// Path to cargo.lock
+    #[clap(short, long, default_value = "Cargo.lock")]
+    path: String,
+    /// Crate to print the locked versions of
+    crate_name: String,
+}
+
+impl Locked {
+    pub fn run(self) -> Result<()> {
+        let lock =
+            Lockfile::load(&self.path).map_err(|error| ConstError::CouldNotLoadLockFileError {
+                path: self.path.clone(),
+                error,
+            })?;
+
+        let mut versions = lock
+            .packages
+            .iter()
+            .filter(|package| package.name.as_str().eq(&self.crate_name))
+            .map(|package| &package.version)
+            .collect::<Vec<_>>();
+
+        if versions.is_empty() {
+            return Err(ConstError::NotInLockfileError {
+                crate_name: self.crate_name,
+                path: self.path,
+            });
+        }
+
+        versions.sort();
+
+        for version in versions {
+            println!("{}", version);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::error::{ConstError, Result};
+
+// cargo_lock does not expose fields it doesn't know about, so the raw file is read
+// again here to pick out anything extra
+#[derive(Deserialize)]
+struct RawLockfile {
+    #[serde(default)]
+    package: Vec<RawPackage>,
+}
+
+#[derive(Deserialize)]
+struct RawPackage {
+    name: String,
+    version: String,
+    #[serde(rename = "rust-version")]
+    rust_version: Option<String>,
+}
+
+// Get the rust-version recorded in the lockfile for each version of the crate, versions
+// without the field are left out so the caller can fall back to crates.io
+pub fn get_lockfile_rust_versions(path: &str, crate_name: &str) -> Result<HashMap<String, String>> {
+    let contents = std::fs::read_to_string(path).map_err(|error| ConstError::OpenFileError {
+        path: path.to_string(),
+        error,
+    })?;
+
+    parse_lockfile_rust_versions(path, &contents, crate_name)
+}
+
+// Same as `get_lockfile_rust_versions` for a lockfile already read, `path` is only used
+// in errors
+pub fn parse_lockfile_rust_versions(
+    path: &str,
+    contents: &str,
+    crate_name: &str,
+) -> Result<HashMap<String, String>> {
+    let lockfile: RawLockfile =
+        toml::from_str(contents).map_err(|error| ConstError::LockFileParseError {
+            path: path.to_string(),
+            error,
+        })?;
+
+    let rust_versions = lockfile
+        .package
+        .into_iter()
+        .filter(|package| package.name.eq(crate_name))
+        .filter_map(|package| Some((package.version, package.rust_version?)))
+        .collect();
+
+    Ok(rust_versions)
+}
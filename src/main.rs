@@ -1,61 +1,272 @@
-use crate::{compat::Compat, utils::print_error};
+// ConstError carries dependent requirements for reporting, which makes it large
+#![allow(clippy::result_large_err)]
+
+use cargo_const::{
+    cache::Cache,
+    compat::{Compat, Count, DependencyKind},
+    config::{
+        layer, read_config_file, read_env_cache_ttl, read_env_count, read_env_include_yanked,
+    },
+    error::{ConstError, Result, EXIT_INVALID_ARGUMENT},
+    locked::Locked,
+    provider::StagedCache,
+    registry::Registry,
+    set_config,
+    utils::{init_logger, print_error, ColorChoice, Date, Ttl, DEFAULT_TIMEOUT, MAX_CACHE_AGE},
+    versions::Versions,
+    why::Why,
+    Config,
+};
 use clap::Parser;
-use std::sync::OnceLock;
 
-pub mod bound;
-pub mod compat;
-pub mod error;
-pub mod provider;
-pub mod utils;
+// The longest --request-delay accepted, in milliseconds
+const MAX_REQUEST_DELAY: u64 = 60_000;
 
-static CONFIG: OnceLock<Config> = OnceLock::new();
+// Each setting is taken from the first of the command line, the environment and the
+// config files to have it, or the built-in default
+fn config_from_args(args: &Args) -> Result<Config> {
+    let file_config = read_config_file()?;
+    if args.quiet && args.verbose.gt(&0) {
+        return Err(ConstError::QuietAndVerboseError);
+    }
 
-pub fn get_config() -> &'static Config {
-    CONFIG.get().as_ref().unwrap()
-}
+    // Json, plain and junit output are meant to be consumed as is so nothing else
+    // should be printed
+    let quiet = args.quiet
+        || match &args.subcommand {
+            SubCommand::Compat(compat) => compat.is_machine_readable(),
+            SubCommand::Locked(_)
+            | SubCommand::Cache(_)
+            | SubCommand::Why(_)
+            | SubCommand::Versions(_) => false,
+        };
 
-fn set_config(args: &Args) {
-    CONFIG.set(Config::from_args(args)).unwrap();
-}
+    let (cli_count, cli_include_yanked, pre, kind) = match &args.subcommand {
+        SubCommand::Compat(compat) => (
+            compat.count().cloned(),
+            compat.include_yanked(),
+            compat.pre(),
+            compat.kind(),
+        ),
+        SubCommand::Locked(_)
+        | SubCommand::Cache(_)
+        | SubCommand::Why(_)
+        | SubCommand::Versions(_) => (None, false, false, DependencyKind::Normal),
+    };
 
-#[derive(Debug)]
-pub struct Config {
-    verbose: bool,
-}
+    let count = layer(
+        cli_count,
+        read_env_count,
+        || file_config.count.map(Count::try_from).transpose(),
+        Count::Count(5),
+    )?;
 
-impl Config {
-    fn from_args(args: &Args) -> Config {
-        Config {
-            verbose: args.verbose,
-        }
+    // The flag can only turn it on, leaving it out defers to the others
+    let include_yanked = layer(
+        cli_include_yanked.then_some(true),
+        || Ok(read_env_include_yanked()),
+        || Ok(file_config.include_yanked),
+        false,
+    )?;
+
+    let cache_ttl = layer(
+        args.cache_ttl,
+        read_env_cache_ttl,
+        || file_config.cache_ttl.as_deref().map(str::parse).transpose(),
+        Ttl(MAX_CACHE_AGE),
+    )?;
+
+    if args.request_delay.gt(&MAX_REQUEST_DELAY) {
+        return Err(ConstError::InvalidRequestDelayArgument {
+            delay: args.request_delay,
+            max: MAX_REQUEST_DELAY,
+        });
+    }
+
+    if args.timeout.is_some_and(|timeout| timeout.eq(&0)) {
+        return Err(ConstError::InvalidTimeoutArgument);
+    }
+
+    let color = layer(
+        args.color,
+        || Ok(None),
+        || file_config.color.as_deref().map(str::parse).transpose(),
+        ColorChoice::Auto,
+    )?;
+
+    let registry = args
+        .registry
+        .as_deref()
+        .or(file_config.registry.as_deref())
+        .map(Registry::find)
+        .transpose()?;
+
+    // The crates.io client is built with its own http client, which can't be given one
+    if args.timeout.is_some() && registry.is_none() {
+        return Err(ConstError::TimeoutWithoutRegistryError);
     }
+
+    Ok(Config {
+        verbosity: args.verbose,
+        quiet,
+        warnings: !args.quiet,
+        progress: args.progress,
+        colored: color.is_colored(),
+        lenient: args.lenient,
+        limit_network: args.limit_network,
+        retries: args.retries,
+        request_delay: args.request_delay,
+        timeout: args.timeout.unwrap_or(DEFAULT_TIMEOUT),
+        as_of: args.as_of,
+        count,
+        include_yanked,
+        pre,
+        kind,
+        concurrency_safe_cache_dir: args.concurrency_safe_cache_dir,
+        offline: args.offline,
+        no_cache: args.no_cache,
+        cache_ttl: cache_ttl.0,
+        max_age_warning: args.max_age_warning.as_ref().map(|age| age.0),
+        registry,
+    })
 }
 
 #[derive(Parser)]
 struct Args {
-    /// Log level
+    /// Print more about what is done, repeat up to -vvv for more detail
+    #[clap(short, long, global = true, parse(from_occurrences))]
+    verbose: u8,
+
+    /// Print only the result or the error that stopped it, no warnings or info
     #[clap(short, long, global = true)]
-    verbose: bool,
+    quiet: bool,
+
+    /// Show how many of the dependents have been fetched so far on a line of its own,
+    /// only when printing to a terminal and without --quiet or -vv
+    #[clap(long, global = true)]
+    progress: bool,
+
+    /// When to color the output, one of "auto", "always" or "never". Auto colors it
+    /// when printing to a terminal and NO_COLOR isn't set [default: auto]
+    #[clap(long, global = true)]
+    color: Option<ColorChoice>,
+
+    /// Skip crate versions that can't be parsed instead of failing
+    #[clap(long, global = true)]
+    lenient: bool,
+
+    /// Fail once this many requests have been sent to crates.io, cache hits don't count
+    #[clap(long, global = true)]
+    limit_network: Option<usize>,
+
+    /// Retry a request up to this many times when it fails in a way that may not happen
+    /// again, such as a dropped connection or being rate limited, waiting longer each time
+    #[clap(long, global = true, default_value = "3")]
+    retries: u32,
+
+    /// Wait at least this many milliseconds between requests to crates.io. Going below
+    /// crates.io's published limit of one request a second may get you throttled
+    #[clap(long, global = true, default_value = "100")]
+    request_delay: u64,
+
+    /// Give up on a request to the registry given by --registry after this many seconds
+    /// [default: 30]. Can't be used with crates.io, which has no way to set one
+    #[clap(long, global = true)]
+    timeout: Option<u64>,
+
+    /// Only use versions published on or before this date, as YYYY-MM-DD
+    #[clap(long, global = true)]
+    as_of: Option<Date>,
+
+    /// Only use what is cached, however old, and fail when something isn't instead of
+    /// fetching it from crates.io
+    #[clap(long, global = true)]
+    offline: bool,
+
+    /// Neither read nor write the cache, fetching everything from crates.io every time
+    #[clap(long, global = true, conflicts_with = "offline")]
+    no_cache: bool,
+
+    /// How long cached entries are used for before being fetched again, such as 30m, 1h
+    /// or 7d, 0 always fetches [default: 7d]
+    #[clap(long, global = true)]
+    cache_ttl: Option<Ttl>,
+
+    /// Warn when a cached entry used is older than this, such as 1d, even if it is within
+    /// the cache ttl or --offline
+    #[clap(long, global = true)]
+    max_age_warning: Option<Ttl>,
+
+    /// Write cache entries to a directory of this process's own and move them to the
+    /// shared cache when it exits cleanly, for runs sharing a cache at the same time
+    #[clap(long, global = true)]
+    concurrency_safe_cache_dir: bool,
+
+    /// Look crates up in this registry instead of crates.io, by its name in the cargo
+    /// config or as a sparse+ index url
+    #[clap(long, global = true)]
+    registry: Option<String>,
 
     #[clap(subcommand)]
     subcommand: SubCommand,
 }
 
+// Only one is ever built, so the size of the largest doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Parser)]
 enum SubCommand {
     Compat(Compat),
+    Locked(Locked),
+    Cache(Cache),
+    Why(Why),
+    Versions(Versions),
 }
 
 fn main() {
-    let args = Args::parse();
+    // Clap exits with 2 on a bad argument, which is EXIT_UNSATISFIABLE here
+    let args = Args::try_parse().unwrap_or_else(|error| {
+        let _ = error.print();
+
+        std::process::exit(match error.use_stderr() {
+            true => EXIT_INVALID_ARGUMENT,
+            false => 0,
+        });
+    });
+
+    match config_from_args(&args) {
+        Ok(config) => {
+            // Nothing has read the config before this
+            let _ = set_config(config);
+
+            init_logger();
+        }
+        Err(error) => {
+            print_error(&error);
+            std::process::exit(error.exit_code());
+        }
+    }
+
+    let result = {
+        // Dropped at the end of this block, before the process exits below
+        let mut staged_cache = StagedCache::default();
 
-    set_config(&args);
+        let result = match args.subcommand {
+            SubCommand::Compat(compat) => compat.run(),
+            SubCommand::Locked(locked) => locked.run(),
+            SubCommand::Cache(cache) => cache.run(),
+            SubCommand::Why(why) => why.run(),
+            SubCommand::Versions(versions) => versions.run(),
+        };
 
-    let result = match args.subcommand {
-        SubCommand::Compat(compat) => compat.run(),
+        staged_cache.set_succeeded(result.is_ok());
+
+        result
     };
 
     if let Err(error) = result {
         print_error(&error);
+
+        // Lets scripts tell why it failed, to retry on the network or stop on a conflict
+        std::process::exit(error.exit_code());
     }
 }
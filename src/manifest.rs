@@ -0,0 +1,256 @@
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Value};
+
+use crate::error::{ConstError, Result};
+
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+// The manifest is expected to sit next to the lockfile
+pub fn get_manifest_path(lock_path: &str) -> PathBuf {
+    Path::new(lock_path).with_file_name("Cargo.toml")
+}
+
+// Set the requirement on `crate_name` to exactly `version` in every dependency table that
+// has it and return the manifest before and after, nothing is written back and the
+// formatting and comments around the requirement are kept
+pub fn pin_dependency(path: &Path, crate_name: &str, version: &str) -> Result<(String, String)> {
+    let (contents, mut document) = read_manifest(path)?;
+
+    let requirement = format!("={}", version);
+
+    let mut found = false;
+
+    for table_name in DEPENDENCY_TABLES {
+        let Some(dependency) = document
+            .get_mut(table_name)
+            .and_then(|table| table.get_mut(crate_name))
+        else {
+            continue;
+        };
+
+        // Either `name = "1.0"` or a table with a `version` key, git and path
+        // dependencies without a version are left alone
+        let value = if dependency.is_str() {
+            dependency.as_value_mut()
+        } else {
+            dependency
+                .as_table_like_mut()
+                .and_then(|table| table.get_mut("version"))
+                .and_then(|version| version.as_value_mut())
+        };
+
+        if let Some(value) = value {
+            let decor = value.decor().clone();
+            *value = Value::from(requirement.as_str());
+            *value.decor_mut() = decor;
+            found = true;
+        }
+    }
+
+    if !found {
+        return Err(ConstError::DependencyNotInManifestError {
+            crate_name: crate_name.to_string(),
+            path: path.to_string_lossy().to_string(),
+        });
+    }
+
+    Ok((contents, document.to_string()))
+}
+
+// Get the requirements on `crate_name` in the dependency tables of the manifest, git and
+// path dependencies without a version have none, each is given with the kind of its table
+pub fn get_dependency_requirements(
+    path: &Path,
+    crate_name: &str,
+) -> Result<Vec<(&'static str, String)>> {
+    let (_, document) = read_manifest(path)?;
+
+    let requirements = DEPENDENCY_TABLES
+        .iter()
+        .filter_map(|table_name| {
+            let dependency = document.get(table_name)?.get(crate_name)?;
+
+            let requirement = match dependency.as_str() {
+                Some(requirement) => requirement,
+                None => dependency.get("version")?.as_str()?,
+            };
+
+            Some((get_table_kind(table_name), requirement.to_string()))
+        })
+        .collect();
+
+    Ok(requirements)
+}
+
+// The kind of the dependencies in a table, as crates.io names it
+fn get_table_kind(table_name: &str) -> &'static str {
+    match table_name {
+        "dev-dependencies" => "dev",
+        "build-dependencies" => "build",
+        _ => "normal",
+    }
+}
+
+// Get the dependency tables of the manifest that have `crate_name` in them
+pub fn get_dependency_tables(path: &Path, crate_name: &str) -> Result<Vec<&'static str>> {
+    let (_, document) = read_manifest(path)?;
+
+    Ok(DEPENDENCY_TABLES
+        .into_iter()
+        .filter(|table_name| {
+            document
+                .get(table_name)
+                .is_some_and(|table| table.get(crate_name).is_some())
+        })
+        .collect())
+}
+
+// Get the name and version of the package in the manifest, a version inherited from the
+// workspace or left out is read as 0.0.0
+pub fn get_package_id(path: &Path) -> Result<(String, String)> {
+    let (_, document) = read_manifest(path)?;
+
+    let package = document.get("package");
+
+    let name = package
+        .and_then(|package| package.get("name"))
+        .and_then(|name| name.as_str())
+        .ok_or_else(|| ConstError::PackageNameNotInManifestError {
+            path: path.to_string_lossy().to_string(),
+        })?;
+
+    let version = package
+        .and_then(|package| package.get("version"))
+        .and_then(|version| version.as_str())
+        .unwrap_or("0.0.0");
+
+    Ok((name.to_string(), version.to_string()))
+}
+
+// Get the rust-version of the package in the manifest, or the one its members inherit
+// from the workspace
+pub fn get_manifest_rust_version(path: &Path) -> Result<Option<String>> {
+    let (_, document) = read_manifest(path)?;
+
+    let rust_version = document
+        .get("package")
+        .and_then(|package| package.get("rust-version"))
+        .and_then(|rust_version| rust_version.as_str())
+        .or_else(|| {
+            document
+                .get("workspace")
+                .and_then(|workspace| workspace.get("package"))
+                .and_then(|package| package.get("rust-version"))
+                .and_then(|rust_version| rust_version.as_str())
+        });
+
+    Ok(rust_version.map(|rust_version| rust_version.to_string()))
+}
+
+// Get the channel pinned in the rust-toolchain.toml next to the manifest, channels like
+// "stable" or "nightly" name no version and are ignored
+pub fn get_toolchain_rust_version(manifest_path: &Path) -> Result<Option<String>> {
+    let path = manifest_path.with_file_name("rust-toolchain.toml");
+
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let (_, document) = read_manifest(&path)?;
+
+    let channel = document
+        .get("toolchain")
+        .and_then(|toolchain| toolchain.get("channel"))
+        .and_then(|channel| channel.as_str())
+        .filter(|channel| channel.starts_with(|c: char| c.is_ascii_digit()));
+
+    Ok(channel.map(|channel| channel.to_string()))
+}
+
+// A member of the workspace, by its package name and the manifest it is declared in
+pub struct WorkspaceMember {
+    pub name: String,
+    pub manifest_path: PathBuf,
+}
+
+// Get the members of the workspace with the root manifest at `path`, including the root
+// package if it has one, a manifest without a workspace table is its only member.
+// Members ending in `/*` cover every directory under them with a manifest
+pub fn get_workspace_members(path: &Path) -> Result<Vec<WorkspaceMember>> {
+    let (_, document) = read_manifest(path)?;
+
+    let root = path.parent().unwrap_or(Path::new(""));
+
+    let mut manifest_paths = Vec::new();
+
+    if document.contains_key("package") {
+        manifest_paths.push(path.to_path_buf());
+    }
+
+    let members = document
+        .get("workspace")
+        .and_then(|workspace| workspace.get("members"))
+        .and_then(|members| members.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|member| member.as_str());
+
+    for member in members {
+        match member.strip_suffix("/*") {
+            Some(parent) => {
+                let Ok(entries) = std::fs::read_dir(root.join(parent)) else {
+                    continue;
+                };
+
+                let mut expanded = entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path().join("Cargo.toml"))
+                    .filter(|manifest_path| manifest_path.is_file())
+                    .collect::<Vec<_>>();
+
+                expanded.sort();
+
+                manifest_paths.extend(expanded);
+            }
+            None => manifest_paths.push(root.join(member).join("Cargo.toml")),
+        }
+    }
+
+    manifest_paths
+        .into_iter()
+        .map(|manifest_path| {
+            let (_, document) = read_manifest(&manifest_path)?;
+
+            let name = document
+                .get("package")
+                .and_then(|package| package.get("name"))
+                .and_then(|name| name.as_str())
+                .ok_or_else(|| ConstError::PackageNameNotInManifestError {
+                    path: manifest_path.to_string_lossy().to_string(),
+                })?
+                .to_string();
+
+            Ok(WorkspaceMember {
+                name,
+                manifest_path,
+            })
+        })
+        .collect()
+}
+
+fn read_manifest(path: &Path) -> Result<(String, DocumentMut)> {
+    let contents = std::fs::read_to_string(path).map_err(|error| ConstError::OpenFileError {
+        path: path.to_string_lossy().to_string(),
+        error,
+    })?;
+
+    let document =
+        contents
+            .parse::<DocumentMut>()
+            .map_err(|error| ConstError::ManifestParseError {
+                path: path.to_string_lossy().to_string(),
+                error,
+            })?;
+
+    Ok((contents, document))
+}
@@ -0,0 +1,113 @@
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path, process::Command};
+
+use crate::{
+    bound::cmp_dependents,
+    error::{ConstError, Result},
+    utils::CRATE_NAME,
+};
+
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<MetadataPackage>,
+    resolve: Option<Resolve>,
+}
+
+#[derive(Deserialize)]
+struct MetadataPackage {
+    id: String,
+    name: String,
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct Resolve {
+    nodes: Vec<Node>,
+}
+
+#[derive(Deserialize)]
+struct Node {
+    id: String,
+    deps: Vec<NodeDependency>,
+}
+
+#[derive(Deserialize)]
+struct NodeDependency {
+    pkg: String,
+}
+
+// The features the project is going to be built with
+pub struct FeatureSelection<'a> {
+    pub features: &'a [String],
+    pub all_features: bool,
+    pub no_default_features: bool,
+}
+
+// Unlike the lockfile, which holds every edge that could be enabled, the graph from cargo
+// metadata only has the edges active under the selected features
+pub fn find_dependents_from_metadata(
+    manifest_path: &Path,
+    crate_to_find: &str,
+    selection: &FeatureSelection,
+) -> Result<Vec<(String, String)>> {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+
+    let mut command = Command::new(cargo);
+
+    command
+        .arg("metadata")
+        .arg("--format-version")
+        .arg("1")
+        .arg("--manifest-path")
+        .arg(manifest_path);
+
+    if !selection.features.is_empty() {
+        command.arg("--features").arg(selection.features.join(","));
+    }
+    if selection.all_features {
+        command.arg("--all-features");
+    }
+    if selection.no_default_features {
+        command.arg("--no-default-features");
+    }
+
+    let output = command
+        .output()
+        .map_err(|error| ConstError::CargoMetadataError(error.to_string()))?;
+
+    if !output.status.success() {
+        return Err(ConstError::CargoMetadataError(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    let metadata: Metadata =
+        serde_json::from_slice(&output.stdout).map_err(ConstError::MetadataParseError)?;
+
+    let packages = metadata
+        .packages
+        .iter()
+        .map(|package| (package.id.as_str(), package))
+        .collect::<HashMap<_, _>>();
+
+    let mut dependents = metadata
+        .resolve
+        .map(|resolve| resolve.nodes)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|node| {
+            node.deps.iter().any(|dependency| {
+                packages
+                    .get(dependency.pkg.as_str())
+                    .is_some_and(|package| package.name.eq(crate_to_find))
+            })
+        })
+        .filter_map(|node| packages.get(node.id.as_str()))
+        .filter(|package| package.name.ne(CRATE_NAME))
+        .map(|package| (package.name.clone(), package.version.clone()))
+        .collect::<Vec<_>>();
+
+    dependents.sort_by(cmp_dependents);
+
+    Ok(dependents)
+}
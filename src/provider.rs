@@ -1,48 +1,91 @@
 use std::{
     fs::OpenOptions,
+    io::Write,
     path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering as AtomicOrdering},
     time::Duration,
 };
 
-use crates_io_api::{SyncClient, Version as CratesIoVersion};
+use crates_io_api::{Error as CratesIoError, SyncClient, Version as CratesIoVersion};
 use semver::{Version as SemverVersion, VersionReq};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::{
+    bound::{MergedBound, ResolutionKey},
     error::{ConstError, Result},
+    get_config,
+    registry::{IndexEntry, SparseIndex},
     utils::{
-        now_as_secs, print_info, print_warning, CRATE_NAME, CRATE_VERSION, MAX_CACHE_AGE,
+        format_age, is_verbose, now_as_secs, print_warning, Date, Progress, Verbosity, CRATE_NAME,
         MY_USER_AGENT,
     },
 };
 
+// The most requests for dependencies in flight at once
+const FETCH_THREADS: usize = 8;
+
+// The wait before the first retry of a failed request, doubled for each retry after
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+// The most time spent waiting between the retries of one request
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(30);
+
+// The length of the CRC32 stored after each cache entry
+const CHECKSUM_LENGTH: usize = 4;
+
+// The version of the layout of what is cached, raised whenever a cached type such as
+// ParsedVersion, ParsedDependency or MergedBound changes shape. Releases that cache the
+// same shapes share a cache, those that don't keep theirs apart
+pub const CACHE_SCHEMA_VERSION: u32 = 1;
+
+// How long a crate or version found not to exist is remembered as missing, short so one
+// published since is soon picked up
+const NOT_FOUND_TTL: u64 = 60 * 15;
+
 #[derive(Deserialize, Serialize, Clone)]
 pub struct ParsedDependency {
     pub crate_id: String,
     pub version_req: VersionReq,
+    // One of "normal", "build" or "dev" as crates.io names them, entries cached before
+    // the kind was kept are read as normal
+    #[serde(default = "normal_kind")]
+    pub kind: String,
 }
 
+fn normal_kind() -> String {
+    "normal".to_string()
+}
+
+// A version as listed by crates.io or an index, before it is parsed
+type VersionListing = (String, bool, Option<String>, Option<String>);
+
 #[derive(Deserialize, Serialize, Clone)]
 pub struct ParsedCrateDependency {
     pub dependencies: Vec<ParsedDependency>,
 }
 
-#[derive(Deserialize, Serialize, Ord, Eq)]
+#[derive(Deserialize, Serialize, Clone, Eq)]
 pub struct ParsedVersion {
     pub yanked: bool,
     pub num: SemverVersion,
     pub rust_version: Option<String>,
+    // Missing from entries cached before it was recorded
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+// Ordered by semver precedence, then by build metadata compared as semver does, so 1.0.0
+// sorts before 1.0.0+a, which sorts before 1.0.0+b. Versions only compare equal when
+// they are the same version, which keeps sorting and searching a list of them unambiguous
+impl Ord for ParsedVersion {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.num.cmp(&other.num)
+    }
 }
 
 impl PartialOrd for ParsedVersion {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        if self.num.lt(&other.num) {
-            Some(std::cmp::Ordering::Less)
-        } else if self.num.eq(&other.num) {
-            Some(std::cmp::Ordering::Equal)
-        } else {
-            Some(std::cmp::Ordering::Greater)
-        }
+        Some(self.cmp(other))
     }
 }
 
@@ -59,13 +102,117 @@ pub struct ParsedCrateVersion {
 
 pub struct Provider {
     client: SyncClient,
+    // Set when a registry other than crates.io is used
+    index: Option<SparseIndex>,
+    // Requests sent so far, only those that missed the cache
+    requests: AtomicUsize,
+    // Lookups of versions or dependencies answered by the cache and those that went on
+    // to be fetched, however many requests that took
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+// How the lookups made through a provider were served
+pub struct ProviderStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub requests: usize,
+}
+
+impl Default for Provider {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Provider {
     pub fn new() -> Provider {
-        let client = SyncClient::new(MY_USER_AGENT, Duration::from_millis(100)).unwrap();
+        let client = SyncClient::new(
+            MY_USER_AGENT,
+            Duration::from_millis(get_config().request_delay),
+        )
+        .unwrap();
+
+        Provider {
+            client,
+            index: get_config().registry.clone().map(SparseIndex::new),
+            requests: AtomicUsize::new(0),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> ProviderStats {
+        ProviderStats {
+            hits: self.hits.load(AtomicOrdering::Relaxed),
+            misses: self.misses.load(AtomicOrdering::Relaxed),
+            requests: self.requests.load(AtomicOrdering::Relaxed),
+        }
+    }
 
-        Provider { client }
+    // Where crates are fetched from, for messages
+    fn source_name(&self) -> &str {
+        match &self.index {
+            Some(index) => index.name(),
+            None => "crates.io",
+        }
+    }
+
+    // Count a request about to be sent, failing instead if the limit has been reached
+    fn count_request(&self) -> Result<()> {
+        let requests = self.requests.fetch_add(1, AtomicOrdering::Relaxed);
+
+        match get_config().limit_network {
+            Some(limit) if requests.ge(&limit) => Err(ConstError::NetworkLimitReachedError(limit)),
+            _ => Ok(()),
+        }
+    }
+
+    // Get the dependencies of each crate, in the same order, with up to FETCH_THREADS
+    // fetched at once. The client still spaces out the requests themselves, so this only
+    // overlaps the waiting on crates.io and the reading of the cache
+    pub fn get_dependencies_of_all(
+        &self,
+        crates: &[(String, String)],
+    ) -> Vec<Result<ParsedCrateDependency>> {
+        let next = AtomicUsize::new(0);
+
+        let threads = FETCH_THREADS.min(crates.len());
+
+        let progress = Progress::new("Fetching dependents", crates.len());
+
+        let mut results = std::thread::scope(|scope| {
+            let workers = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut results = Vec::new();
+
+                        loop {
+                            let index = next.fetch_add(1, AtomicOrdering::Relaxed);
+
+                            let Some((crate_name, crate_version)) = crates.get(index) else {
+                                break;
+                            };
+
+                            results.push((index, self.get_dependencies(crate_name, crate_version)));
+
+                            progress.advance();
+                        }
+
+                        results
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        results.sort_by_key(|(index, _)| *index);
+
+        results.into_iter().map(|(_, result)| result).collect()
     }
 
     pub fn get_dependencies(
@@ -73,171 +220,536 @@ impl Provider {
         crate_name: &str,
         crate_version: &str,
     ) -> Result<ParsedCrateDependency> {
-        let mut data_dir = get_data_location();
+        let mut data_dir = get_cache_location();
 
         if let Some(data_dir) = data_dir.as_mut() {
             data_dir.push("dependencies");
-            data_dir.push(crate_name);
-            data_dir.push(crate_version);
+            data_dir.push(encode_path_component(crate_name));
+            data_dir.push(encode_path_component(crate_version));
+
+            if let Some((cache_time, crate_dependencies)) = read_from_cache(data_dir) {
+                if is_fresh(cache_time) {
+                    log::trace!("Cache hit at {:?}", data_dir);
+
+                    warn_if_old(
+                        cache_time,
+                        &format!("dependencies of {} {}", crate_name, crate_version),
+                    );
+
+                    self.hits.fetch_add(1, AtomicOrdering::Relaxed);
 
-            if let Ok((cache_time, crate_dependencies)) = read_from_file::<_, (u64, _)>(data_dir) {
-                if cache_time.gt(&(now_as_secs() - MAX_CACHE_AGE)) {
                     return Ok(crate_dependencies);
                 }
             }
+
+            log::trace!("Cache miss at {:?}", data_dir);
         };
 
+        let not_found_dir = get_not_found_location(&["dependencies", crate_name, crate_version]);
+
+        if is_known_missing(not_found_dir.as_ref()) {
+            self.hits.fetch_add(1, AtomicOrdering::Relaxed);
+
+            return Err(ConstError::CrateNotFoundError {
+                crate_name: crate_name.to_string(),
+                crate_version: Some(crate_version.to_string()),
+            });
+        }
+
+        if get_config().offline {
+            return Err(ConstError::OfflineCacheMissError {
+                crate_name: crate_name.to_string(),
+                crate_version: Some(crate_version.to_string()),
+            });
+        }
+
+        log::debug!(
+            "Fetching the dependencies of {} {} from {}",
+            crate_name,
+            crate_version,
+            self.source_name()
+        );
+
+        self.misses.fetch_add(1, AtomicOrdering::Relaxed);
+
+        let result = self.fetch_and_store(data_dir.as_ref(), || {
+            self.count_request()?;
+
+            let dependencies = match &self.index {
+                Some(index) => fetch_index_dependencies(index, crate_name, crate_version),
+                None => self.fetch_crates_io_dependencies(crate_name, crate_version),
+            }?;
+
+            Ok(ParsedCrateDependency { dependencies })
+        });
+
+        if let Err(ConstError::CrateNotFoundError { .. }) = &result {
+            remember_missing(not_found_dir.as_ref());
+        }
+
+        result
+    }
+
+    fn fetch_crates_io_dependencies(
+        &self,
+        crate_name: &str,
+        crate_version: &str,
+    ) -> Result<Vec<ParsedDependency>> {
         let dependencies = self
             .client
             .crate_dependencies(crate_name, crate_version)
-            .map_err(|error| ConstError::CrateDependencyFetchError(error))?;
+            .map_err(|error| {
+                classify_fetch_error(
+                    error,
+                    crate_name,
+                    Some(crate_version),
+                    ConstError::CrateDependencyFetchError,
+                )
+            })?;
 
-        let result = dependencies
+        dependencies
             .into_iter()
             .map(|dependency| {
-                let crates_io_api::Dependency { crate_id, req, .. } = dependency;
+                let crates_io_api::Dependency {
+                    crate_id,
+                    req,
+                    kind,
+                    ..
+                } = dependency;
 
                 Ok(ParsedDependency {
                     crate_id,
                     version_req: VersionReq::parse(&req)
-                        .map_err(|error| ConstError::VersionReqParseError(error))?,
+                        .map_err(ConstError::VersionReqParseError)?,
+                    kind,
                 })
             })
-            .collect::<Result<Vec<ParsedDependency>>>();
+            .collect()
+    }
 
-        let parsed_crate_dependencies = ParsedCrateDependency {
-            dependencies: result?,
-        };
+    // Versions published after the --as-of date are left out as if they didn't exist yet
+    pub fn get_versions(&self, crate_to_find: &str) -> Result<ParsedCrateVersion> {
+        let mut crate_versions = self.fetch_versions(crate_to_find)?;
 
-        match data_dir.as_ref() {
-            Some(data_dir) => {
-                let result = write_to_file(data_dir, (now_as_secs(), &parsed_crate_dependencies));
-                if result.is_err() {
-                    let message = format!(
-                        "Could not create cache at {:?}\n{}",
-                        data_dir,
-                        "Repeated requests without caching increases chances of rate limiting"
-                    );
+        let Some(as_of) = get_config().as_of else {
+            return Ok(crate_versions);
+        };
 
-                    print_warning(&message);
-                } else {
-                    let message = format!("Cache succesfully created at {:?}", data_dir);
+        let mut undated = 0;
 
-                    print_info(&message);
+        crate_versions.versions.retain(|version| {
+            match version.created_at.as_deref().and_then(Date::from_timestamp) {
+                Some(created_at) => created_at.le(&as_of),
+                None => {
+                    undated += 1;
+                    true
                 }
             }
-            None => {
-                let message = format!(
-                    "Could not access directory at {:?}\n{}",
-                    data_dir,
-                    "Repeated requests without caching increases chances of rate limiting"
-                );
+        });
 
-                print_warning(&message);
-            }
+        if undated.gt(&0) {
+            let message = format!(
+                "Kept {} version(s) of {} with no known publish date, clearing the cache may help",
+                undated, crate_to_find
+            );
+
+            print_warning(&message);
         }
 
-        Ok(parsed_crate_dependencies)
+        Ok(crate_versions)
     }
 
-    pub fn get_versions(&self, crate_to_find: &str) -> Result<ParsedCrateVersion> {
-        let mut data_dir = get_data_location();
+    fn fetch_versions(&self, crate_to_find: &str) -> Result<ParsedCrateVersion> {
+        let mut data_dir = get_cache_location();
 
         if let Some(data_dir) = data_dir.as_mut() {
             data_dir.push("versions");
-            data_dir.push(crate_to_find);
+            data_dir.push(encode_path_component(crate_to_find));
+
+            if let Some((cache_time, crate_versions)) = read_from_cache(data_dir) {
+                if is_fresh(cache_time) {
+                    log::trace!("Cache hit at {:?}", data_dir);
+
+                    warn_if_old(cache_time, &format!("versions of {}", crate_to_find));
+
+                    self.hits.fetch_add(1, AtomicOrdering::Relaxed);
 
-            if let Ok((cache_time, crate_versions)) = read_from_file::<_, (u64, _)>(data_dir) {
-                if cache_time.gt(&(now_as_secs() - MAX_CACHE_AGE)) {
                     return Ok(crate_versions);
                 }
             }
+
+            log::trace!("Cache miss at {:?}", data_dir);
         };
 
-        let result = self
-            .client
-            .get_crate(crate_to_find)
-            .map_err(|error| ConstError::CrateInfoFetchError(error))?;
+        let not_found_dir = get_not_found_location(&["versions", crate_to_find]);
 
-        let result = result
-            .versions
-            .into_iter()
-            .map(|version| {
-                let CratesIoVersion {
-                    num,
-                    yanked,
-                    rust_version,
-                    ..
-                } = version;
+        if is_known_missing(not_found_dir.as_ref()) {
+            self.hits.fetch_add(1, AtomicOrdering::Relaxed);
 
-                let semver_version = SemverVersion::parse(&num)
-                    .map_err(|error| ConstError::VersionParseError(error))?;
+            return Err(ConstError::CrateNotFoundError {
+                crate_name: crate_to_find.to_string(),
+                crate_version: None,
+            });
+        }
 
-                Ok(ParsedVersion {
-                    num: semver_version,
-                    yanked,
-                    rust_version,
+        if get_config().offline {
+            return Err(ConstError::OfflineCacheMissError {
+                crate_name: crate_to_find.to_string(),
+                crate_version: None,
+            });
+        }
+
+        log::debug!(
+            "Fetching the versions of {} from {}",
+            crate_to_find,
+            self.source_name()
+        );
+
+        self.misses.fetch_add(1, AtomicOrdering::Relaxed);
+
+        let result = self.fetch_and_store(data_dir.as_ref(), || {
+            self.count_request()?;
+
+            parse_version_list(crate_to_find, self.fetch_version_list(crate_to_find)?)
+        });
+
+        if let Err(ConstError::CrateNotFoundError { .. }) = &result {
+            remember_missing(not_found_dir.as_ref());
+        }
+
+        result
+    }
+
+    // The number, whether it was yanked, the min rust version and the publish date of
+    // every version of the crate. The index doesn't record when a version was published
+    fn fetch_version_list(&self, crate_to_find: &str) -> Result<Vec<VersionListing>> {
+        let versions = match &self.index {
+            Some(index) => index
+                .fetch(crate_to_find)?
+                .into_iter()
+                .map(|entry| (entry.vers, entry.yanked, entry.rust_version, None))
+                .collect::<Vec<_>>(),
+            None => self
+                .client
+                .get_crate(crate_to_find)
+                .map_err(|error| {
+                    classify_fetch_error(
+                        error,
+                        crate_to_find,
+                        None,
+                        ConstError::CrateInfoFetchError,
+                    )
+                })?
+                .versions
+                .into_iter()
+                .map(|version| {
+                    let CratesIoVersion {
+                        num,
+                        yanked,
+                        rust_version,
+                        created_at,
+                        ..
+                    } = version;
+
+                    (num, yanked, rust_version, Some(created_at.to_string()))
                 })
+                .collect(),
+        };
+
+        Ok(versions)
+    }
+
+    // Run `fetch` again after a failure that might not happen twice, waiting as long as
+    // `retry_delays` says before each retry, until it stops failing that way or the
+    // delays run out
+    fn with_retries<T>(&self, mut fetch: impl FnMut() -> Result<T>) -> Result<T> {
+        let max_retries = get_config().retries;
+
+        let mut delays = retry_delays(max_retries).enumerate();
+
+        loop {
+            match fetch() {
+                Err(error) if error.is_transient() => {
+                    let Some((retry, delay)) = delays.next() else {
+                        return Err(error);
+                    };
+
+                    if is_verbose(Verbosity::Info) {
+                        let message = format!(
+                            "{}\nRetrying in {}ms, retry {} of {}",
+                            error,
+                            delay.as_millis(),
+                            retry + 1,
+                            max_retries
+                        );
+
+                        print_warning(&message);
+                    }
+
+                    std::thread::sleep(delay);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // Fetch with retries and cache the response once one succeeds, the attempts that
+    // failed before it leave nothing behind
+    fn fetch_and_store<T: Serialize>(
+        &self,
+        data_dir: Option<&PathBuf>,
+        fetch: impl FnMut() -> Result<T>,
+    ) -> Result<T> {
+        let value = self.with_retries(fetch)?;
+
+        store_in_cache(data_dir, &value);
+
+        Ok(value)
+    }
+
+    // The key is stored along with the bound, so one that only shares the checksum of
+    // the key isn't handed back
+    pub fn get_merged_bound(&self, key: &ResolutionKey) -> Option<MergedBound> {
+        let data_dir = get_merged_bound_location(key)?;
+
+        let (cache_time, (cached_key, merged_bound)) =
+            read_from_cache::<(ResolutionKey, MergedBound)>(&data_dir)?;
+
+        if is_fresh(cache_time) && cached_key.eq(key) {
+            Some(merged_bound)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_merged_bound(&self, key: &ResolutionKey, merged_bound: &MergedBound) {
+        let Some(data_dir) = get_merged_bound_location(key) else {
+            return;
+        };
+
+        let result = write_to_file(&data_dir, (now_as_secs(), (key, merged_bound)));
+
+        if result.is_err() {
+            let message = format!("Could not cache the resolved bound at {:?}", data_dir);
+
+            print_warning(&message);
+        } else {
+            log::debug!("Cache succesfully created at {:?}", data_dir);
+        }
+    }
+}
+
+// Get the dependencies of one version of the crate from the index of a registry, a
+// renamed dependency is given by the name of the crate it really is
+fn fetch_index_dependencies(
+    index: &SparseIndex,
+    crate_name: &str,
+    crate_version: &str,
+) -> Result<Vec<ParsedDependency>> {
+    let entry = index
+        .fetch(crate_name)?
+        .into_iter()
+        .find(|entry: &IndexEntry| entry.vers.eq(crate_version))
+        .ok_or_else(|| ConstError::CrateNotFoundError {
+            crate_name: crate_name.to_string(),
+            crate_version: Some(crate_version.to_string()),
+        })?;
+
+    entry
+        .deps
+        .into_iter()
+        .map(|dependency| {
+            Ok(ParsedDependency {
+                crate_id: dependency.package.unwrap_or(dependency.name),
+                version_req: VersionReq::parse(&dependency.req)
+                    .map_err(ConstError::VersionReqParseError)?,
+                kind: dependency.kind.unwrap_or_else(normal_kind),
             })
-            .collect::<Result<Vec<ParsedVersion>>>();
+        })
+        .collect()
+}
 
-        let parsed_crate_versions = ParsedCrateVersion { versions: result? };
+// Tell apart a crate or version that doesn't exist, which won't change by asking again,
+// from a failure on the way to or within crates.io that might, anything else is left to
+// `fallback`
+fn classify_fetch_error(
+    error: CratesIoError,
+    crate_name: &str,
+    crate_version: Option<&str>,
+    fallback: fn(CratesIoError) -> ConstError,
+) -> ConstError {
+    match &error {
+        CratesIoError::NotFound(_) => ConstError::CrateNotFoundError {
+            crate_name: crate_name.to_string(),
+            crate_version: crate_version.map(str::to_string),
+        },
+        CratesIoError::Http(http_error) if http_error.is_timeout() => {
+            ConstError::RequestTimeoutError {
+                registry: "crates.io".to_string(),
+            }
+        }
+        CratesIoError::Http(http_error)
+            if http_error.is_connect()
+                || http_error
+                    .status()
+                    .is_some_and(|status| status.is_server_error() || status.as_u16().eq(&429)) =>
+        {
+            ConstError::TransientFetchError(error)
+        }
+        _ => fallback(error),
+    }
+}
+
+// How long to wait before each retry, twice as long as before each time, with at most
+// `max_retries` of them and none that would take the wait past MAX_RETRY_WAIT in all
+fn retry_delays(max_retries: u32) -> impl Iterator<Item = Duration> {
+    std::iter::successors(Some(RETRY_BASE_DELAY), |delay| delay.checked_mul(2))
+        .scan(Duration::ZERO, |waited, delay| {
+            *waited += delay;
+            (*waited).le(&MAX_RETRY_WAIT).then_some(delay)
+        })
+        .take(max_retries as usize)
+}
+
+// Parse the version list fetched for a crate, with --lenient a version that isn't valid
+// semver is skipped rather than failing the whole list
+fn parse_version_list(
+    crate_to_find: &str,
+    versions: Vec<VersionListing>,
+) -> Result<ParsedCrateVersion> {
+    let mut skipped = 0;
 
-        match data_dir.as_ref() {
-            Some(data_dir) => {
-                let result = write_to_file(data_dir, (now_as_secs(), &parsed_crate_versions));
-                if result.is_err() {
+    let result = versions
+        .into_iter()
+        .filter_map(|(num, yanked, rust_version, created_at)| {
+            match SemverVersion::parse(&num) {
+                Ok(semver_version) => Some(Ok(ParsedVersion {
+                    num: semver_version,
+                    yanked,
+                    rust_version,
+                    created_at,
+                })),
+                // A single bad historical version shouldn't stop the rest from being used
+                Err(error) if get_config().lenient => {
                     let message = format!(
-                        "Could not create cache at {:?}\n{}",
-                        data_dir,
-                        "Repeated requests without caching increases chances of rate limiting"
+                        "Skipping version {} of {} which could not be parsed: {}",
+                        num, crate_to_find, error
                     );
 
                     print_warning(&message);
-                } else {
-                    let message = format!("Cache succesfully created at {:?}", data_dir);
 
-                    print_info(&message);
+                    skipped += 1;
+
+                    None
                 }
+                Err(error) => Some(Err(ConstError::VersionParseError(error))),
             }
-            None => {
+        })
+        .collect::<Result<Vec<ParsedVersion>>>();
+
+    if skipped.gt(&0) {
+        let message = format!(
+            "Skipped {} version(s) of {} that could not be parsed",
+            skipped, crate_to_find
+        );
+
+        print_warning(&message);
+    }
+
+    Ok(ParsedCrateVersion { versions: result? })
+}
+
+// Only called with a fully fetched and parsed response, so a failed request never
+// leaves anything behind in the cache for a later run to pick up
+fn store_in_cache<T: Serialize>(data_dir: Option<&PathBuf>, value: &T) {
+    if get_config().no_cache {
+        return;
+    }
+
+    match data_dir {
+        Some(data_dir) => {
+            let result = write_to_file(data_dir, (now_as_secs(), value));
+            if result.is_err() {
                 let message = format!(
-                    "Could not access directory at {:?}\n{}",
+                    "Could not create cache at {:?}\n{}",
                     data_dir,
                     "Repeated requests without caching increases chances of rate limiting"
                 );
 
                 print_warning(&message);
+            } else {
+                log::debug!("Cache succesfully created at {:?}", data_dir);
             }
         }
+        None => {
+            let message = format!(
+                "Could not access directory at {:?}\n{}",
+                data_dir, "Repeated requests without caching increases chances of rate limiting"
+            );
 
-        Ok(parsed_crate_versions)
+            print_warning(&message);
+        }
     }
 }
 
-fn read_from_file<P, T>(path: P) -> Result<T>
+// The cache only spares requests, so an entry that can't be read is warned about and
+// fetched again like a missing one rather than failing the run
+fn read_from_cache<T: DeserializeOwned>(data_dir: &Path) -> Option<(u64, T)> {
+    match read_from_file::<_, (u64, T)>(data_dir) {
+        Ok(entry) => entry,
+        Err(error) => {
+            print_warning(&format!("{}\nIgnoring it as if nothing was cached", error));
+
+            None
+        }
+    }
+}
+
+// Entries end with a CRC32 of the rest, one that doesn't match was cut short or damaged
+// on the way to disk. A missing entry is `None`, one that is there but can't be read is
+// an error
+fn read_from_file<P, T>(path: P) -> Result<Option<T>>
 where
     T: DeserializeOwned,
     P: AsRef<Path>,
 {
-    let result = std::fs::File::open(path.as_ref()).map_err(|error| ConstError::OpenFileError {
-        path: path.as_ref().to_string_lossy().to_string(),
-        error,
-    });
+    // Entries written earlier in this run are newer than the shared ones
+    let staged = get_staged_path(path.as_ref()).filter(|staged| staged.is_file());
+    let path = staged.as_deref().unwrap_or(path.as_ref());
+
+    let contents = match std::fs::read(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind().eq(&std::io::ErrorKind::NotFound) => return Ok(None),
+        Err(error) => {
+            return Err(ConstError::OpenFileError {
+                path: path.to_string_lossy().to_string(),
+                error,
+            })
+        }
+    };
+
+    let corrupt = || ConstError::CorruptCacheEntryError {
+        path: path.to_string_lossy().to_string(),
+    };
 
-    let file = result?;
+    let split = contents
+        .len()
+        .checked_sub(CHECKSUM_LENGTH)
+        .ok_or_else(corrupt)?;
 
-    let buffer = std::io::BufReader::new(file);
+    let (payload, checksum) = contents.split_at(split);
 
-    let result = serde_cbor::from_reader::<T, _>(buffer).map_err(|error| {
-        ConstError::DeserializeFromFileError {
+    if crc32fast::hash(payload).to_le_bytes().ne(checksum) {
+        return Err(corrupt());
+    }
+
+    serde_cbor::from_slice::<T>(payload)
+        .map(Some)
+        .map_err(|error| ConstError::DeserializeFromFileError {
             type_name: std::any::type_name::<T>(),
-            path: path.as_ref().to_string_lossy().to_string(),
+            path: path.to_string_lossy().to_string(),
             error,
-        }
-    });
-
-    result
+        })
 }
 
 fn write_to_file<P, T>(path: P, value: T) -> Result<()>
@@ -245,7 +757,10 @@ where
     P: AsRef<Path>,
     T: Serialize,
 {
-    if let Some(parent) = path.as_ref().parent() {
+    let staged = get_staged_path(path.as_ref());
+    let path = staged.as_deref().unwrap_or(path.as_ref());
+
+    if let Some(parent) = path.parent() {
         if !parent.exists() {
             if let Err(error) = std::fs::create_dir_all(parent) {
                 return Err(ConstError::CreateParentDirectoryError {
@@ -256,33 +771,678 @@ where
             // Log that the parent directory is created
         }
     }
+
+    // Written to a file of its own beside the entry and renamed over it once complete,
+    // so a run that is killed part way never leaves a partial entry where it is read
+    let temporary = get_temporary_path(path);
+
     let file = OpenOptions::new()
         .write(true)
         .create(true)
-        .open(&path)
+        .truncate(true)
+        .open(&temporary)
         .map_err(|error| ConstError::OpenFileError {
-            path: path.as_ref().to_string_lossy().to_string(),
+            path: temporary.to_string_lossy().to_string(),
             error,
         })?;
 
-    let writer = std::io::BufWriter::new(file);
+    let mut writer = std::io::BufWriter::new(file);
 
-    let result =
-        serde_cbor::to_writer(writer, &value).map_err(|error| ConstError::SerializeToFileError {
+    let result = serde_cbor::to_vec(&value)
+        .map_err(|error| ConstError::SerializeToFileError {
             type_name: std::any::type_name::<T>(),
-            path: path.as_ref().to_string_lossy().to_string(),
+            path: path.to_string_lossy().to_string(),
             error,
+        })
+        .and_then(|mut payload| {
+            payload.extend(crc32fast::hash(&payload).to_le_bytes());
+
+            writer
+                .write_all(&payload)
+                .and_then(|_| writer.flush())
+                .map_err(|error| ConstError::WriteFileError {
+                    path: temporary.to_string_lossy().to_string(),
+                    error,
+                })
+        })
+        .and_then(|_| {
+            drop(writer);
+
+            std::fs::rename(&temporary, path).map_err(|error| ConstError::WriteFileError {
+                path: path.to_string_lossy().to_string(),
+                error,
+            })
         });
 
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temporary);
+    }
+
     result
 }
 
-fn get_data_location() -> Option<PathBuf> {
+// A hidden file beside the entry, unique to this process and write, as several threads
+// may write the same entry at once
+fn get_temporary_path(path: &Path) -> PathBuf {
+    static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+    let file_name = path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        file_name,
+        std::process::id(),
+        WRITES.fetch_add(1, AtomicOrdering::Relaxed)
+    ))
+}
+
+// Crate names and versions as they can be used for a file or directory name anywhere.
+// Anything but lowercase letters, digits, '-', '_' and '.' is percent-encoded, such as the
+// '+' of build metadata. Uppercase letters are too, as 1.0.0-RC and 1.0.0-rc are different
+// versions that a case-insensitive filesystem would otherwise store in one place
+fn encode_path_component(part: &str) -> String {
+    let mut encoded = String::with_capacity(part.len());
+
+    for byte in part.bytes() {
+        match byte {
+            b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' => encoded.push(byte as char),
+            byte => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+// Offline any cached entry is used however old, as it's all there is, otherwise a ttl
+// of 0 means nothing cached is used
+fn is_fresh(cache_time: u64) -> bool {
+    if get_config().offline {
+        return true;
+    }
+
+    let cache_ttl = get_config().cache_ttl;
+
+    // Saturating, as a clock near the epoch or a ttl longer than the time since would
+    // otherwise underflow. An entry stamped after now, written before the clock was set
+    // back, counts as fresh
+    cache_ttl.gt(&0) && cache_time.gt(&now_as_secs().saturating_sub(cache_ttl))
+}
+
+// Fresh or not, an entry older than --max-age-warning may be missing newer releases
+fn warn_if_old(cache_time: u64, described: &str) {
+    let Some(max_age) = get_config().max_age_warning else {
+        return;
+    };
+
+    let age = now_as_secs().saturating_sub(cache_time);
+
+    if age.gt(&max_age) {
+        print_warning(&format!(
+            "The cached {} are {} old, older than --max-age-warning {}",
+            described,
+            format_age(age),
+            format_age(max_age)
+        ));
+    }
+}
+
+// Crates and versions found not to exist are marked apart from what is cached for those
+// that do, so a marker is never read as an entry
+fn get_not_found_location(parts: &[&str]) -> Option<PathBuf> {
+    let mut data_dir = get_cache_location()?;
+
+    data_dir.push("not-found");
+    data_dir.extend(parts.iter().map(|part| encode_path_component(part)));
+
+    Some(data_dir)
+}
+
+// Whether the crate or version was found not to exist within the last NOT_FOUND_TTL, or
+// the --cache-ttl if that is shorter
+fn is_known_missing(data_dir: Option<&PathBuf>) -> bool {
+    let Some(data_dir) = data_dir else {
+        return false;
+    };
+
+    let Ok(Some((cache_time, ()))) = read_from_file::<_, (u64, ())>(data_dir) else {
+        return false;
+    };
+
+    let ttl = NOT_FOUND_TTL.min(get_config().cache_ttl);
+
+    if ttl.eq(&0) || cache_time.le(&now_as_secs().saturating_sub(ttl)) {
+        return false;
+    }
+
+    log::trace!("Known missing at {:?}", data_dir);
+
+    true
+}
+
+fn remember_missing(data_dir: Option<&PathBuf>) {
+    let Some(data_dir) = data_dir else {
+        return;
+    };
+
+    if write_to_file(data_dir, (now_as_secs(), ())).is_err() {
+        log::debug!("Could not mark as missing at {:?}", data_dir);
+    }
+}
+
+// Merged bounds are stored under the CRC32 of their key as CBOR, which unlike the std
+// hashers is the same for every build so the entries outlive an update of Rust
+fn get_merged_bound_location(key: &ResolutionKey) -> Option<PathBuf> {
+    let mut data_dir = get_cache_location()?;
+
+    data_dir.push("resolutions");
+    data_dir.push(encode_path_component(&key.crate_name));
+    data_dir.push(resolution_file_name(key)?);
+
+    Some(data_dir)
+}
+
+fn resolution_file_name(key: &ResolutionKey) -> Option<String> {
+    let encoded = serde_cbor::to_vec(key).ok()?;
+
+    Some(format!("{:08x}", crc32fast::hash(&encoded)))
+}
+
+// With --concurrency-safe-cache-dir entries are written under a directory of this
+// process's own, so processes sharing a cache never write the same file
+fn get_staging_location() -> Option<PathBuf> {
+    if !get_config().concurrency_safe_cache_dir {
+        return None;
+    }
+
+    let mut data_dir = get_data_location()?;
+
+    data_dir.push("staging");
+    data_dir.push(std::process::id().to_string());
+
+    Some(data_dir)
+}
+
+// Where the entry at `path` in the shared cache is staged, if staging
+fn get_staged_path(path: &Path) -> Option<PathBuf> {
+    let relative = path.strip_prefix(get_data_location()?).ok()?;
+
+    Some(get_staging_location()?.join(relative))
+}
+
+// Finishes the cache staged by this process when dropped, so it is done however the run
+// returns, a panic included. Exiting the process below main would skip it, so errors are
+// returned up to main instead
+#[derive(Default)]
+pub struct StagedCache {
+    succeeded: bool,
+}
+
+impl StagedCache {
+    pub fn set_succeeded(&mut self, succeeded: bool) {
+        self.succeeded = succeeded;
+    }
+}
+
+impl Drop for StagedCache {
+    fn drop(&mut self) {
+        finish_staged_cache(self.succeeded && !std::thread::panicking());
+    }
+}
+
+// Move the entries staged by this process into the shared cache once it has finished
+// cleanly, a failed run only removes them. Each move replaces a whole entry at once so
+// another process reads either the old entry or the new one
+fn finish_staged_cache(succeeded: bool) {
+    let (Some(staging_dir), Some(data_dir)) = (get_staging_location(), get_data_location()) else {
+        return;
+    };
+
+    if !staging_dir.exists() {
+        return;
+    }
+
+    if succeeded {
+        if let Err(error) = promote_dir(&staging_dir, &staging_dir, &data_dir) {
+            let message = format!(
+                "Could not move the cache staged at {:?} to {:?}: {}",
+                staging_dir, data_dir, error
+            );
+
+            print_warning(&message);
+        }
+    }
+
+    let _ = std::fs::remove_dir_all(&staging_dir);
+
+    // Only goes once no other process is staging
+    if let Some(parent) = staging_dir.parent() {
+        let _ = std::fs::remove_dir(parent);
+    }
+}
+
+fn promote_dir(dir: &Path, staging_dir: &Path, data_dir: &Path) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            promote_dir(&path, staging_dir, data_dir)?;
+            continue;
+        }
+
+        let Ok(relative) = path.strip_prefix(staging_dir) else {
+            continue;
+        };
+
+        let destination = data_dir.join(relative);
+
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::rename(&path, &destination)?;
+    }
+
+    Ok(())
+}
+
+// Where entries are read from and written to, with --no-cache there is nowhere so
+// nothing is read and no directory is created
+fn get_cache_location() -> Option<PathBuf> {
+    if get_config().no_cache {
+        return None;
+    }
+
+    get_data_location()
+}
+
+pub fn get_data_location() -> Option<PathBuf> {
     let mut data_dir = dirs::data_dir();
 
     if let Some(data_dir) = data_dir.as_mut() {
-        data_dir.push(format!("{}-{}", CRATE_NAME, CRATE_VERSION));
+        data_dir.push(format!("{}-cache-v{}", CRATE_NAME, CACHE_SCHEMA_VERSION));
+
+        // Crates from another registry are kept apart from those from crates.io, which
+        // may share their names
+        if let Some(registry) = &get_config().registry {
+            data_dir.push("registries");
+            data_dir.push(encode_path_component(&registry.host()));
+        }
     }
 
     data_dir
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::compat::DependencyKind;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    fn versions(nums: &[&str]) -> ParsedCrateVersion {
+        ParsedCrateVersion {
+            versions: nums
+                .iter()
+                .map(|num| ParsedVersion {
+                    yanked: false,
+                    num: num.parse().unwrap(),
+                    rust_version: None,
+                    created_at: None,
+                })
+                .collect(),
+        }
+    }
+
+    // The fixtures were written by an earlier build, a change that breaks reading them
+    // has to raise CACHE_SCHEMA_VERSION and write them again
+    #[test]
+    fn reads_golden_versions() {
+        let (cache_time, parsed) =
+            read_from_file::<_, (u64, ParsedCrateVersion)>(fixture("parsed_crate_version.cbor"))
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(cache_time, 1_700_000_000);
+
+        let [stable, prerelease] = parsed.versions.as_slice() else {
+            panic!("expected two versions");
+        };
+
+        assert!(!stable.yanked);
+        assert_eq!(stable.num, SemverVersion::new(1, 2, 3));
+        assert_eq!(stable.rust_version.as_deref(), Some("1.70"));
+        assert_eq!(
+            stable.created_at.as_deref(),
+            Some("2024-01-01T00:00:00.000000+00:00")
+        );
+
+        assert!(prerelease.yanked);
+        assert_eq!(prerelease.num.to_string(), "1.2.4-beta.1+build.5");
+        assert_eq!(prerelease.rust_version, None);
+        assert_eq!(prerelease.created_at, None);
+    }
+
+    #[test]
+    fn reads_golden_dependencies() {
+        let (cache_time, parsed) = read_from_file::<_, (u64, ParsedCrateDependency)>(fixture(
+            "parsed_crate_dependency.cbor",
+        ))
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(cache_time, 1_700_000_000);
+
+        let [normal, build] = parsed.dependencies.as_slice() else {
+            panic!("expected two dependencies");
+        };
+
+        assert_eq!(normal.crate_id, "serde");
+        assert_eq!(normal.version_req.to_string(), "^1.0.100");
+        assert_eq!(normal.kind, "normal");
+
+        assert_eq!(build.crate_id, "cc");
+        assert_eq!(build.version_req.to_string(), ">=1.0, <2");
+        assert_eq!(build.kind, "build");
+    }
+
+    // Garbage with a checksum that matches still has to fail to decode rather than be
+    // taken as an entry
+    #[test]
+    fn garbage_bytes_are_a_cache_miss_then_rewritten() {
+        let directory = tempfile::tempdir().unwrap();
+        let data_dir = directory.path().join("versions");
+
+        let mut garbage = b"not a cache entry".to_vec();
+        garbage.extend(crc32fast::hash(&garbage).to_le_bytes());
+
+        for contents in [b"not a cache entry".to_vec(), garbage] {
+            std::fs::write(&data_dir, contents).unwrap();
+
+            assert!(read_from_cache::<ParsedCrateVersion>(&data_dir).is_none());
+        }
+
+        write_to_file(&data_dir, (now_as_secs(), versions(&["1.0.0"]))).unwrap();
+
+        let (_, parsed) = read_from_cache::<ParsedCrateVersion>(&data_dir).unwrap();
+        assert_eq!(parsed.versions.len(), 1);
+    }
+
+    #[test]
+    fn retries_wait_twice_as_long_each_time() {
+        assert_eq!(
+            retry_delays(3).collect::<Vec<_>>(),
+            [500, 1000, 2000].map(Duration::from_millis)
+        );
+        assert_eq!(retry_delays(0).count(), 0);
+    }
+
+    // MAX_RETRY_WAIT of 30 seconds in all stops the waits after the fifth, however many
+    // retries are allowed
+    #[test]
+    fn retries_stop_before_waiting_past_the_cap() {
+        let delays = retry_delays(u32::MAX).collect::<Vec<_>>();
+
+        assert_eq!(
+            delays,
+            [500, 1000, 2000, 4000, 8000].map(Duration::from_millis)
+        );
+        assert!(delays.iter().sum::<Duration>().le(&MAX_RETRY_WAIT));
+    }
+
+    // An entry stamped after now was written before the clock was set back, it is still
+    // fresh and working out its age must not underflow
+    #[test]
+    fn entries_from_the_future_are_fresh() {
+        assert!(is_fresh(u64::MAX));
+        assert!(is_fresh(now_as_secs() + 60 * 60));
+        assert!(is_fresh(now_as_secs()));
+        assert!(!is_fresh(0));
+
+        warn_if_old(u64::MAX, "versions");
+
+        let directory = tempfile::tempdir().unwrap();
+        let data_dir = directory.path().join("versions");
+
+        write_to_file(&data_dir, (u64::MAX, versions(&["1.0.0"]))).unwrap();
+
+        let (cache_time, _) = read_from_cache::<ParsedCrateVersion>(&data_dir).unwrap();
+        assert!(is_fresh(cache_time));
+    }
+
+    #[test]
+    fn path_components_encode_what_filesystems_mishandle() {
+        assert_eq!(encode_path_component("1.0.0+build.1"), "1.0.0%2Bbuild.1");
+        assert_eq!(encode_path_component("1.0.0-RC.1"), "1.0.0-%52%43.1");
+        assert_eq!(encode_path_component("serde_json"), "serde_json");
+
+        let encoded = [
+            "1.0.0",
+            "1.0.0+build.1",
+            "1.0.0+build.2",
+            "1.0.0-rc",
+            "1.0.0-RC",
+        ]
+        .map(encode_path_component);
+
+        for (index, component) in encoded.iter().enumerate() {
+            assert!(!encoded[index + 1..].contains(component), "{}", component);
+        }
+    }
+
+    #[test]
+    fn versions_with_build_metadata_round_trip_through_the_cache() {
+        let directory = tempfile::tempdir().unwrap();
+        let crate_dir = directory.path().join("dependencies").join("helper");
+
+        for version in ["1.0.0", "1.0.0+build.1"] {
+            let data_dir = crate_dir.join(encode_path_component(version));
+
+            write_to_file(&data_dir, (now_as_secs(), versions(&[version]))).unwrap();
+        }
+
+        for version in ["1.0.0", "1.0.0+build.1"] {
+            let data_dir = crate_dir.join(encode_path_component(version));
+            let (_, cached) = read_from_cache::<ParsedCrateVersion>(&data_dir).unwrap();
+
+            assert_eq!(cached.versions[0].num.to_string(), version);
+        }
+    }
+
+    #[test]
+    fn versions_differing_in_build_metadata_sort_in_one_order() {
+        let expected = [
+            "0.9.0",
+            "1.0.0",
+            "1.0.0+a",
+            "1.0.0+b",
+            "1.0.1-rc.1",
+            "1.0.1",
+        ];
+
+        for order in [
+            [
+                "1.0.0+b",
+                "1.0.0",
+                "1.0.1",
+                "1.0.0+a",
+                "0.9.0",
+                "1.0.1-rc.1",
+            ],
+            [
+                "1.0.0+a",
+                "1.0.1-rc.1",
+                "1.0.0+b",
+                "1.0.1",
+                "1.0.0",
+                "0.9.0",
+            ],
+        ] {
+            let mut sorted = versions(&order).versions;
+            sorted.sort();
+
+            let sorted = sorted
+                .iter()
+                .map(|version| version.num.to_string())
+                .collect::<Vec<_>>();
+
+            assert_eq!(sorted, expected);
+        }
+
+        let [plain, a, b] = versions(&["1.0.0", "1.0.0+a", "1.0.0+b"])
+            .versions
+            .try_into()
+            .ok()
+            .unwrap();
+
+        assert!(plain.ne(&a) && a.ne(&b));
+        assert!(plain.lt(&a) && a.lt(&b));
+        assert_eq!(a.cmp(&a.clone()), std::cmp::Ordering::Equal);
+        assert_eq!(plain.num.cmp_precedence(&b.num), std::cmp::Ordering::Equal);
+    }
+
+    fn resolution_key() -> ResolutionKey {
+        ResolutionKey {
+            crate_name: "helper".to_string(),
+            dependents: vec![("app-a".to_string(), "1.0.0".to_string())],
+            kind: DependencyKind::Normal,
+            features: vec!["std".to_string()],
+            all_features: false,
+            no_default_features: false,
+            package: None,
+            pre: false,
+        }
+    }
+
+    fn timeout() -> ConstError {
+        ConstError::RequestTimeoutError {
+            registry: "crates.io".to_string(),
+        }
+    }
+
+    #[test]
+    fn caches_the_response_that_follows_a_failed_attempt() {
+        let directory = tempfile::tempdir().unwrap();
+        let data_dir = directory.path().join("versions");
+
+        let mut attempts = 0;
+
+        let fetched = Provider::new()
+            .fetch_and_store(Some(&data_dir), || {
+                attempts += 1;
+
+                match attempts {
+                    1 => Err(timeout()),
+                    _ => Ok(versions(&["1.0.0", "1.1.0"])),
+                }
+            })
+            .unwrap();
+
+        assert_eq!(attempts, 2);
+        assert_eq!(fetched.versions.len(), 2);
+
+        let (_, cached) = read_from_file::<_, (u64, ParsedCrateVersion)>(&data_dir)
+            .unwrap()
+            .unwrap();
+
+        let cached = cached
+            .versions
+            .iter()
+            .map(|version| version.num.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(cached, ["1.0.0", "1.1.0"]);
+    }
+
+    #[test]
+    fn caches_nothing_when_every_attempt_fails() {
+        let directory = tempfile::tempdir().unwrap();
+        let data_dir = directory.path().join("versions");
+
+        let result = Provider::new().fetch_and_store(Some(&data_dir), || {
+            Err::<ParsedCrateVersion, _>(ConstError::VersionParseError(
+                SemverVersion::parse("not a version").unwrap_err(),
+            ))
+        });
+
+        assert!(result.is_err());
+        assert!(std::fs::read_dir(directory.path())
+            .unwrap()
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn flipped_byte_is_a_cache_miss_then_refetched() {
+        let directory = tempfile::tempdir().unwrap();
+        let data_dir = directory.path().join("versions");
+
+        write_to_file(&data_dir, (now_as_secs(), versions(&["1.0.0"]))).unwrap();
+
+        let mut contents = std::fs::read(&data_dir).unwrap();
+        contents[8] ^= 0xff;
+        std::fs::write(&data_dir, contents).unwrap();
+
+        assert!(matches!(
+            read_from_file::<_, (u64, ParsedCrateVersion)>(&data_dir),
+            Err(ConstError::CorruptCacheEntryError { .. })
+        ));
+        assert!(read_from_cache::<ParsedCrateVersion>(&data_dir).is_none());
+
+        Provider::new()
+            .fetch_and_store(Some(&data_dir), || Ok(versions(&["1.0.0", "1.1.0"])))
+            .unwrap();
+
+        let (_, cached) = read_from_cache::<ParsedCrateVersion>(&data_dir).unwrap();
+
+        assert_eq!(cached.versions.len(), 2);
+    }
+
+    #[test]
+    fn truncated_entry_is_a_cache_miss() {
+        let directory = tempfile::tempdir().unwrap();
+        let data_dir = directory.path().join("versions");
+
+        std::fs::write(&data_dir, [0x82]).unwrap();
+
+        assert!(read_from_cache::<ParsedCrateVersion>(&data_dir).is_none());
+    }
+
+    // Entries written by an earlier build have to be found under the same name
+    #[test]
+    fn resolution_file_name_is_stable() {
+        assert_eq!(
+            resolution_file_name(&resolution_key()).as_deref(),
+            Some("c43eb9b5")
+        );
+    }
+
+    #[test]
+    fn resolution_file_name_covers_every_input() {
+        let base = resolution_file_name(&resolution_key());
+
+        let changes: [fn(&mut ResolutionKey); 6] = [
+            |key| key.kind = DependencyKind::All,
+            |key| key.features.push("alloc".to_string()),
+            |key| key.all_features = true,
+            |key| key.no_default_features = true,
+            |key| key.package = Some("member".to_string()),
+            |key| key.pre = true,
+        ];
+
+        for change in changes {
+            let mut key = resolution_key();
+
+            change(&mut key);
+
+            assert_ne!(resolution_file_name(&key), base);
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use reqwest::{
+    blocking::Client as HttpClient,
+    header::{HeaderMap, HeaderValue, USER_AGENT},
+    StatusCode, Url,
+};
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    error::{ConstError, Result},
+    get_config,
+    utils::MY_USER_AGENT,
+};
+
+// A registry other than crates.io, only read through its sparse index as that is all
+// that can be asked of any registry
+#[derive(Debug, Clone)]
+pub struct Registry {
+    pub name: String,
+    // The url of the index, without the sparse+ prefix and always ending in a slash
+    pub index: Url,
+}
+
+impl Registry {
+    // Either a sparse index url given as is, or the name of a registry looked up the way
+    // cargo does, in $CARGO_REGISTRIES_<NAME>_INDEX and then the cargo config files
+    pub fn find(name: &str) -> Result<Registry> {
+        if name.starts_with("sparse+") {
+            return Registry::from_index(name, name);
+        }
+
+        let variable = format!(
+            "CARGO_REGISTRIES_{}_INDEX",
+            name.to_uppercase().replace('-', "_")
+        );
+
+        if let Ok(index) = std::env::var(variable) {
+            return Registry::from_index(name, &index);
+        }
+
+        for path in get_cargo_config_paths() {
+            if let Some(index) = read_registry_index(&path, name)? {
+                return Registry::from_index(name, &index);
+            }
+        }
+
+        Err(ConstError::RegistryNotFoundError {
+            registry: name.to_string(),
+        })
+    }
+
+    fn from_index(name: &str, index: &str) -> Result<Registry> {
+        let unsupported = || ConstError::UnsupportedRegistryIndexError {
+            registry: name.to_string(),
+            index: index.to_string(),
+        };
+
+        let index = index.strip_prefix("sparse+").ok_or_else(unsupported)?;
+
+        let index = match index.ends_with('/') {
+            true => index.to_string(),
+            false => format!("{}/", index),
+        };
+
+        Ok(Registry {
+            name: name.to_string(),
+            index: Url::parse(&index).map_err(|_| unsupported())?,
+        })
+    }
+
+    // Used to keep what is cached from each registry apart
+    pub fn host(&self) -> String {
+        let host = self.index.host_str().unwrap_or("local");
+
+        match self.index.port() {
+            Some(port) => format!("{}-{}", host, port),
+            None => host.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct IndexEntry {
+    pub vers: String,
+    pub deps: Vec<IndexDependency>,
+    pub yanked: bool,
+    pub rust_version: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct IndexDependency {
+    pub name: String,
+    pub req: String,
+    // Left out for normal dependencies
+    pub kind: Option<String>,
+    // The real name of a renamed dependency, `name` is then what it is renamed to
+    pub package: Option<String>,
+}
+
+pub struct SparseIndex {
+    registry: Registry,
+    client: HttpClient,
+}
+
+impl SparseIndex {
+    pub fn new(registry: Registry) -> SparseIndex {
+        let mut headers = HeaderMap::new();
+
+        headers.insert(USER_AGENT, HeaderValue::from_static(MY_USER_AGENT));
+
+        let client = HttpClient::builder()
+            .default_headers(headers)
+            .timeout(Duration::from_secs(get_config().timeout))
+            .build()
+            .unwrap();
+
+        SparseIndex { registry, client }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.registry.name
+    }
+
+    // Get every version of the crate listed in the index, one JSON object per line
+    pub fn fetch(&self, crate_name: &str) -> Result<Vec<IndexEntry>> {
+        let url = self
+            .registry
+            .index
+            .join(&get_index_path(crate_name))
+            .map_err(|_| ConstError::CrateNotFoundError {
+                crate_name: crate_name.to_string(),
+                crate_version: None,
+            })?;
+
+        let unreachable = |error: reqwest::Error| match error.is_timeout() {
+            true => ConstError::RequestTimeoutError {
+                registry: self.registry.name.clone(),
+            },
+            false => ConstError::RegistryUnreachableError {
+                registry: self.registry.name.clone(),
+                error,
+            },
+        };
+
+        let response = self.client.get(url).send().map_err(unreachable)?;
+
+        // Some registries answer 403 or 410 rather than 404 for crates they don't have
+        if matches!(
+            response.status(),
+            StatusCode::NOT_FOUND | StatusCode::FORBIDDEN | StatusCode::GONE
+        ) {
+            return Err(ConstError::CrateNotFoundError {
+                crate_name: crate_name.to_string(),
+                crate_version: None,
+            });
+        }
+
+        let contents = response
+            .error_for_status()
+            .and_then(|response| response.text())
+            .map_err(unreachable)?;
+
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line).map_err(|error| ConstError::RegistryIndexParseError {
+                    registry: self.registry.name.clone(),
+                    crate_name: crate_name.to_string(),
+                    error,
+                })
+            })
+            .collect()
+    }
+}
+
+// Where the index keeps a crate, following the layout cargo uses
+fn get_index_path(crate_name: &str) -> String {
+    let name = crate_name.to_lowercase();
+
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+// The cargo config files that apply from here, nearest first, then the one in cargo's
+// home
+fn get_cargo_config_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    if let Ok(current_dir) = std::env::current_dir() {
+        for dir in current_dir.ancestors() {
+            paths.push(dir.join(".cargo").join("config.toml"));
+            paths.push(dir.join(".cargo").join("config"));
+        }
+    }
+
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")));
+
+    if let Some(cargo_home) = cargo_home {
+        paths.push(cargo_home.join("config.toml"));
+        paths.push(cargo_home.join("config"));
+    }
+
+    paths
+}
+
+// Get `registries.<name>.index` from a cargo config file, a missing file has none
+fn read_registry_index(path: &Path, name: &str) -> Result<Option<String>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let contents = std::fs::read_to_string(path).map_err(|error| ConstError::OpenFileError {
+        path: path.to_string_lossy().to_string(),
+        error,
+    })?;
+
+    let config: toml::Table =
+        toml::from_str(&contents).map_err(|error| ConstError::CargoConfigParseError {
+            path: path.to_string_lossy().to_string(),
+            error,
+        })?;
+
+    let index = config
+        .get("registries")
+        .and_then(|registries| registries.get(name))
+        .and_then(|registry| registry.get("index"))
+        .and_then(|index| index.as_str())
+        .map(str::to_string);
+
+    Ok(index)
+}
@@ -0,0 +1,859 @@
+use owo_colors::OwoColorize;
+use semver::{Version, VersionReq};
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::{
+    error::{ConstError, Result},
+    provider::ParsedVersion,
+    report::{CompatReport, PinPreview, ReportDependent},
+    utils::{escape_xml, CRATE_NAME},
+};
+
+// Turns the results of a run into the text of one output format, the caller decides
+// where it goes
+pub trait Renderer {
+    // The versions found for a crate
+    fn render_versions(&self, report: &CompatReport) -> Result<String>;
+
+    // The requirement merged from the dependents of a crate
+    fn render_bound(&self, crate_name: &str, requirement: &VersionReq) -> Result<String>;
+
+    // The manifest change --pin-dry-run would make for a crate
+    fn render_pin(&self, pin: &PinPreview) -> Result<String>;
+
+    // The reason no version could be found for a crate, printed to stderr by `Output` so
+    // the document on stdout stays whole
+    fn render_error(&self, crate_name: &str, error: &ConstError) -> String;
+
+    // Something worth knowing about how the versions of a crate were found, printed the
+    // same way as the errors
+    fn render_warning(&self, crate_name: &str, message: &str) -> String;
+
+    // Whether the rendered crates only make sense once wrapped by render_document, rather
+    // than printed one at a time
+    fn is_document(&self) -> bool {
+        false
+    }
+
+    // Anything that has to wrap the rendered crates to make a whole document
+    fn render_document(&self, items: &[String]) -> String {
+        items.join("\n")
+    }
+}
+
+// The rendered crates of a run, printed as they come unless the renderer has to wrap them
+// all in one document or they go to a file, either of which is written by `finish`
+pub struct Output {
+    renderer: Box<dyn Renderer>,
+    // Stdout when there is none
+    path: Option<PathBuf>,
+    items: Vec<String>,
+}
+
+impl Output {
+    pub fn new(renderer: Box<dyn Renderer>) -> Output {
+        Output {
+            renderer,
+            path: None,
+            items: Vec::new(),
+        }
+    }
+
+    pub fn to_file(renderer: Box<dyn Renderer>, path: PathBuf) -> Output {
+        Output {
+            renderer,
+            path: Some(path),
+            items: Vec::new(),
+        }
+    }
+
+    pub fn renderer(&self) -> &dyn Renderer {
+        self.renderer.as_ref()
+    }
+
+    pub fn error(&self, crate_name: &str, error: &ConstError) {
+        eprintln!("{}", self.renderer.render_error(crate_name, error));
+    }
+
+    pub fn warning(&self, crate_name: &str, message: &str) {
+        eprintln!("{}", self.renderer.render_warning(crate_name, message));
+    }
+
+    pub fn push(&mut self, item: String) {
+        match self.path.is_none() && !self.renderer.is_document() {
+            true => println!("{}", item),
+            false => self.items.push(item),
+        }
+    }
+
+    pub fn finish(self) -> Result<()> {
+        let Some(path) = self.path else {
+            if self.renderer.is_document() {
+                println!("{}", self.renderer.render_document(&self.items));
+            }
+
+            return Ok(());
+        };
+
+        let document = format!("{}\n", self.renderer.render_document(&self.items));
+
+        std::fs::write(&path, document).map_err(|error| ConstError::WriteFileError {
+            path: path.to_string_lossy().to_string(),
+            error,
+        })
+    }
+}
+
+pub struct TextRenderer {
+    pub colored: bool,
+    // Name the crate in each header, for when more than one crate is listed
+    pub named: bool,
+    // Follow each version with its min rust version where known
+    pub annotated: bool,
+}
+
+impl TextRenderer {
+    fn render_section<I>(&self, header: &str, items: I) -> String
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let items = items.into_iter().map(|item| match self.colored {
+            true => item.bold().blue().to_string(),
+            false => item,
+        });
+
+        let header = match self.colored {
+            true => header.bold().cyan().to_string(),
+            false => header.to_string(),
+        };
+
+        format!("{}:\n\n{}", header, items.collect::<Vec<_>>().join("\n"))
+    }
+}
+
+impl Renderer for TextRenderer {
+    fn render_versions(&self, report: &CompatReport) -> Result<String> {
+        let lines = report.versions.iter().map(|version| {
+            let min_rust_version_message = version
+                .rust_version
+                .as_ref()
+                .filter(|_| self.annotated)
+                .map(|version| format!("    min-rust-version = {}", version))
+                .unwrap_or_default();
+            format!("{}{}", &version.num, min_rust_version_message)
+        });
+
+        let header = match (self.named, report.locked) {
+            (_, Some(locked)) => format!(
+                "Compatible versions of {} found for the dependents locked to {}",
+                report.crate_name, locked
+            ),
+            (true, None) => format!("Compatible versions of {} found", report.crate_name),
+            (false, None) => "Compatible versions found".to_string(),
+        };
+
+        Ok(self.render_section(&header, lines))
+    }
+
+    fn render_bound(&self, crate_name: &str, requirement: &VersionReq) -> Result<String> {
+        let header = if self.named {
+            format!("Compatible requirement for {} found", crate_name)
+        } else {
+            "Compatible requirement found".to_string()
+        };
+
+        Ok(self.render_section(&header, [requirement.to_string()]))
+    }
+
+    fn render_pin(&self, pin: &PinPreview) -> Result<String> {
+        if !pin.up_to_date {
+            return Ok(pin.diff.trim_end().to_string());
+        }
+
+        Ok(self.render_section(
+            "Pin is up to date",
+            [format!("{} = \"={}\"", pin.name, pin.version)],
+        ))
+    }
+
+    fn render_error(&self, _crate_name: &str, error: &ConstError) -> String {
+        match self.colored {
+            true => format!("{}: {}", "Error".bold().red(), error.bright_red()),
+            false => format!("Error: {}", error),
+        }
+    }
+
+    // The same as the logger prints them
+    fn render_warning(&self, _crate_name: &str, message: &str) -> String {
+        match self.colored {
+            true => format!("{}: {}", "Warning".bold().yellow(), message.bright_yellow()),
+            false => format!("Warning: {}", message),
+        }
+    }
+}
+
+// One version per line and nothing else
+pub struct PlainRenderer;
+
+impl Renderer for PlainRenderer {
+    fn render_versions(&self, report: &CompatReport) -> Result<String> {
+        Ok(report
+            .versions
+            .iter()
+            .map(|version| version.num.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    fn render_bound(&self, _crate_name: &str, requirement: &VersionReq) -> Result<String> {
+        Ok(requirement.to_string())
+    }
+
+    // The diff alone, or the pin as it already is
+    fn render_pin(&self, pin: &PinPreview) -> Result<String> {
+        match pin.up_to_date {
+            true => Ok(format!("{} = \"={}\"", pin.name, pin.version)),
+            false => Ok(pin.diff.trim_end().to_string()),
+        }
+    }
+
+    fn render_error(&self, crate_name: &str, error: &ConstError) -> String {
+        format!("{}: {}", crate_name, error)
+    }
+
+    fn render_warning(&self, crate_name: &str, message: &str) -> String {
+        format!("{}: warning: {}", crate_name, message)
+    }
+}
+
+// Also used for TOML, which leaves out a missing min_rust_version as it has no null. The
+// fields after it are only set in the JSON array, where nothing else says which crate the
+// version is of
+#[derive(Serialize)]
+struct JsonVersion<'a> {
+    version: String,
+    yanked: bool,
+    min_rust_version: Option<&'a String>,
+    // Only when more than one crate is listed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    // Only when the crate is locked at more than one version, each gets its own versions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    locked: Option<&'a Version>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dependents: Option<&'a [ReportDependent<'a>]>,
+}
+
+impl<'a> JsonVersion<'a> {
+    fn new(version: &'a ParsedVersion) -> JsonVersion<'a> {
+        JsonVersion {
+            version: version.num.to_string(),
+            yanked: version.yanked,
+            min_rust_version: version.rust_version.as_ref(),
+            name: None,
+            locked: None,
+            dependents: None,
+        }
+    }
+}
+
+// The same shape whether or not the dependents are asked for, they are only left out
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    name: &'a str,
+    // Only when the crate is locked at more than one version, each gets an entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    locked: Option<&'a Version>,
+    versions: Vec<JsonVersion<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dependents: Option<&'a [ReportDependent<'a>]>,
+}
+
+impl<'a> JsonReport<'a> {
+    fn new(report: &'a CompatReport, include_requirements: bool) -> JsonReport<'a> {
+        let versions = report
+            .versions
+            .iter()
+            .map(|version| JsonVersion::new(version))
+            .collect();
+
+        let dependents = match include_requirements {
+            true => Some(report.dependents.as_slice()),
+            false => None,
+        };
+
+        JsonReport {
+            name: report.crate_name,
+            locked: report.locked,
+            versions,
+            dependents,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonBound<'a> {
+    name: &'a str,
+    requirement: String,
+}
+
+#[derive(Serialize)]
+struct JsonError<'a> {
+    name: &'a str,
+    error: String,
+}
+
+#[derive(Serialize)]
+struct JsonWarning<'a> {
+    name: &'a str,
+    warning: &'a str,
+}
+
+// A TOML document can't be an array, so each crate is a table of a `crates` array, which
+// also keeps the crates of a run valid TOML when written one after the other
+#[derive(Serialize)]
+struct TomlCrates<T> {
+    crates: Vec<T>,
+}
+
+// One flat array of the versions of every crate, each an object of its own
+pub struct JsonRenderer {
+    // Name the crate in each version, for when more than one crate is listed
+    pub named: bool,
+    // Include the dependents and their requirements in each version
+    pub include_requirements: bool,
+}
+
+impl Renderer for JsonRenderer {
+    // The versions of the crate as elements of the array, so no brackets around them
+    fn render_versions(&self, report: &CompatReport) -> Result<String> {
+        let versions = report
+            .versions
+            .iter()
+            .map(|version| {
+                let version = JsonVersion {
+                    name: self.named.then_some(report.crate_name),
+                    locked: report.locked,
+                    dependents: self
+                        .include_requirements
+                        .then_some(report.dependents.as_slice()),
+                    ..JsonVersion::new(version)
+                };
+
+                serde_json::to_string_pretty(&version)
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(ConstError::SerializeOutputError)?;
+
+        Ok(versions.join(",\n"))
+    }
+
+    fn render_bound(&self, crate_name: &str, requirement: &VersionReq) -> Result<String> {
+        serde_json::to_string_pretty(&JsonBound {
+            name: crate_name,
+            requirement: requirement.to_string(),
+        })
+        .map_err(ConstError::SerializeOutputError)
+    }
+
+    fn render_pin(&self, pin: &PinPreview) -> Result<String> {
+        serde_json::to_string_pretty(pin).map_err(ConstError::SerializeOutputError)
+    }
+
+    fn is_document(&self) -> bool {
+        true
+    }
+
+    // A crate without a version listed adds no element
+    fn render_document(&self, items: &[String]) -> String {
+        let items = items
+            .iter()
+            .filter(|item| !item.is_empty())
+            .map(|item| format!("  {}", item.replace('\n', "\n  ")))
+            .collect::<Vec<_>>();
+
+        if items.is_empty() {
+            return "[]".to_string();
+        }
+
+        format!("[\n{}\n]", items.join(",\n"))
+    }
+
+    // One line each on stderr, so they can be read as they come
+    fn render_error(&self, crate_name: &str, error: &ConstError) -> String {
+        // Serializing two strings can't fail
+        serde_json::to_string(&JsonError {
+            name: crate_name,
+            error: error.to_string(),
+        })
+        .unwrap_or_default()
+    }
+
+    fn render_warning(&self, crate_name: &str, message: &str) -> String {
+        serde_json::to_string(&JsonWarning {
+            name: crate_name,
+            warning: message,
+        })
+        .unwrap_or_default()
+    }
+}
+
+// The same fields as the JSON, with the versions as an array of tables so each version
+// gets a table of its own
+pub struct TomlRenderer {
+    // Include the dependents and their requirements next to the versions
+    pub include_requirements: bool,
+}
+
+impl Renderer for TomlRenderer {
+    fn render_versions(&self, report: &CompatReport) -> Result<String> {
+        toml::to_string(&TomlCrates {
+            crates: vec![JsonReport::new(report, self.include_requirements)],
+        })
+        .map_err(ConstError::SerializeTomlOutputError)
+    }
+
+    fn render_bound(&self, crate_name: &str, requirement: &VersionReq) -> Result<String> {
+        toml::to_string(&TomlCrates {
+            crates: vec![JsonBound {
+                name: crate_name,
+                requirement: requirement.to_string(),
+            }],
+        })
+        .map_err(ConstError::SerializeTomlOutputError)
+    }
+
+    fn render_pin(&self, pin: &PinPreview) -> Result<String> {
+        toml::to_string(&TomlCrates { crates: vec![pin] })
+            .map_err(ConstError::SerializeTomlOutputError)
+    }
+
+    fn render_error(&self, crate_name: &str, error: &ConstError) -> String {
+        // Serializing two strings can't fail
+        toml::to_string(&TomlCrates {
+            crates: vec![JsonError {
+                name: crate_name,
+                error: error.to_string(),
+            }],
+        })
+        .unwrap_or_default()
+    }
+
+    fn render_warning(&self, crate_name: &str, message: &str) -> String {
+        toml::to_string(&TomlCrates {
+            crates: vec![JsonWarning {
+                name: crate_name,
+                warning: message,
+            }],
+        })
+        .unwrap_or_default()
+    }
+}
+
+// Each crate is a test case that passes when a version is found, errors that mean no
+// version fits are failures and any other is an error
+pub struct JunitRenderer;
+
+impl Renderer for JunitRenderer {
+    fn render_versions(&self, report: &CompatReport) -> Result<String> {
+        Ok(format!(
+            "  <testcase name=\"{}\" classname=\"compat\"/>",
+            escape_xml(report.crate_name)
+        ))
+    }
+
+    fn render_bound(&self, crate_name: &str, _requirement: &VersionReq) -> Result<String> {
+        Ok(format!(
+            "  <testcase name=\"{}\" classname=\"compat\"/>",
+            escape_xml(crate_name)
+        ))
+    }
+
+    // A pin that is out of date is an error rather than a failure, a version was found
+    fn render_pin(&self, pin: &PinPreview) -> Result<String> {
+        if pin.up_to_date {
+            return Ok(format!(
+                "  <testcase name=\"{}\" classname=\"compat\"/>",
+                escape_xml(pin.name)
+            ));
+        }
+
+        let error = ConstError::PinOutOfDateError {
+            pins: vec![(pin.name.to_string(), pin.version.clone())],
+            path: pin.path.clone(),
+        };
+
+        Ok(self.render_error(pin.name, &error))
+    }
+
+    fn render_error(&self, crate_name: &str, error: &ConstError) -> String {
+        let kind = if error.is_unsatisfiable() {
+            "failure"
+        } else {
+            "error"
+        };
+
+        let message = escape_xml(&error.to_string());
+
+        format!(
+            "  <testcase name=\"{}\" classname=\"compat\">\n    \
+            <{} message=\"{}\">{}</{}>\n  </testcase>",
+            escape_xml(crate_name),
+            kind,
+            message,
+            message,
+            kind
+        )
+    }
+
+    // Only the errors are test cases, a warning doesn't fail a crate
+    fn render_warning(&self, crate_name: &str, message: &str) -> String {
+        format!("{}: warning: {}", crate_name, message)
+    }
+
+    fn is_document(&self) -> bool {
+        true
+    }
+
+    // The messages are escaped so only the tags written above can match
+    fn render_document(&self, items: &[String]) -> String {
+        let body = items
+            .iter()
+            .map(|item| format!("{}\n", item))
+            .collect::<String>();
+
+        let tests = body.matches("<testcase ").count();
+        let failures = body.matches("<failure ").count();
+        let errors = body.matches("<error ").count();
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\">\n\
+            {}</testsuite>",
+            CRATE_NAME, tests, failures, errors, body
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::report::ReportBound;
+
+    fn parsed_version(num: &str, rust_version: Option<&str>) -> ParsedVersion {
+        ParsedVersion {
+            yanked: false,
+            num: num.parse().unwrap(),
+            rust_version: rust_version.map(str::to_string),
+            created_at: None,
+        }
+    }
+
+    fn dependent(name: &'static str) -> ReportDependent<'static> {
+        ReportDependent {
+            name,
+            version: "1.0.0",
+            requirement: "^0.4".to_string(),
+        }
+    }
+
+    // Newest first, as the renderers are handed them
+    fn report(versions: &[ParsedVersion]) -> CompatReport<'_> {
+        CompatReport {
+            crate_name: "helper",
+            bound: ReportBound {
+                requirement: ">=0.4.0, <0.5.0".to_string(),
+                oldest: &versions[versions.len() - 1].num,
+                newest: &versions[0].num,
+            },
+            versions: versions.iter().collect(),
+            dependents: vec![dependent("app-a"), dependent("app-b")],
+            lower_dependent: dependent("app-a"),
+            upper_dependent: dependent("app-b"),
+            locked: None,
+            warnings: Vec::new(),
+            elapsed_ms: 0,
+        }
+    }
+
+    fn versions() -> Vec<ParsedVersion> {
+        vec![
+            parsed_version("0.4.2", Some("1.70")),
+            parsed_version("0.4.1", None),
+            parsed_version("0.4.0", Some("1.60")),
+        ]
+    }
+
+    #[test]
+    fn plain_prints_only_the_versions() {
+        let versions = versions();
+
+        let output = PlainRenderer.render_versions(&report(&versions)).unwrap();
+
+        assert_eq!(output, "0.4.2\n0.4.1\n0.4.0");
+    }
+
+    #[test]
+    fn several_crates_make_one_json_document() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("versions.json");
+
+        let renderer = JsonRenderer {
+            named: true,
+            include_requirements: false,
+        };
+
+        let versions = versions();
+
+        let mut output = Output::to_file(Box::new(renderer), path.clone());
+
+        for _ in 0..2 {
+            output.push(
+                output
+                    .renderer()
+                    .render_versions(&report(&versions))
+                    .unwrap(),
+            );
+        }
+
+        output.finish().unwrap();
+
+        let document = std::fs::read_to_string(path).unwrap();
+
+        let versions = serde_json::from_str::<serde_json::Value>(&document).unwrap();
+
+        assert_eq!(versions.as_array().map(Vec::len), Some(6));
+        assert_eq!(versions[3]["version"], "0.4.2");
+        assert_eq!(versions[3]["name"], "helper");
+    }
+
+    fn missing() -> ConstError {
+        ConstError::NoMatchingDependentError("missing <&>".to_string())
+    }
+
+    // Each renderer over the same crates, as a whole document
+    fn render_all(renderer: &dyn Renderer) -> String {
+        let versions = versions();
+
+        let items = [
+            renderer.render_versions(&report(&versions)).unwrap(),
+            renderer
+                .render_bound("helper", &"^0.4".parse().unwrap())
+                .unwrap(),
+        ];
+
+        renderer.render_document(&items)
+    }
+
+    // The errors are test cases of the document, rather than printed on their own
+    fn render_junit() -> String {
+        let versions = versions();
+
+        let items = [
+            JunitRenderer.render_versions(&report(&versions)).unwrap(),
+            JunitRenderer
+                .render_bound("helper", &"^0.4".parse().unwrap())
+                .unwrap(),
+            JunitRenderer.render_error("missing", &missing()),
+        ];
+
+        JunitRenderer.render_document(&items)
+    }
+
+    // The versions of one crate, the output of --format json as it is most often run
+    fn render_json(renderer: &JsonRenderer) -> String {
+        let versions = versions();
+
+        renderer.render_document(&[renderer.render_versions(&report(&versions)).unwrap()])
+    }
+
+    #[test]
+    fn json_is_a_flat_array_of_versions() {
+        let document = render_json(&JsonRenderer {
+            named: false,
+            include_requirements: false,
+        });
+
+        let versions = serde_json::from_str::<serde_json::Value>(&document).unwrap();
+
+        assert_eq!(
+            versions[1],
+            serde_json::json!({
+                "version": "0.4.1",
+                "yanked": false,
+                "min_rust_version": null,
+            })
+        );
+        assert_eq!(versions.as_array().map(Vec::len), Some(3));
+    }
+
+    #[test]
+    fn json_adds_the_name_and_dependents_when_asked() {
+        let document = render_json(&JsonRenderer {
+            named: true,
+            include_requirements: true,
+        });
+
+        let versions = serde_json::from_str::<serde_json::Value>(&document).unwrap();
+
+        assert_eq!(versions[0]["version"], "0.4.2");
+        assert_eq!(versions[0]["name"], "helper");
+        assert_eq!(versions[2]["dependents"][0]["name"], "app-a");
+    }
+
+    #[test]
+    fn json_leaves_out_crates_without_versions() {
+        let renderer = JsonRenderer {
+            named: true,
+            include_requirements: false,
+        };
+
+        let versions = versions();
+
+        let mut report = report(&versions);
+        report.versions.clear();
+
+        let empty = renderer.render_versions(&report).unwrap();
+
+        assert_eq!(renderer.render_document(std::slice::from_ref(&empty)), "[]");
+
+        let document = renderer.render_document(&[
+            empty,
+            renderer
+                .render_bound("helper", &"^0.4".parse().unwrap())
+                .unwrap(),
+        ]);
+
+        let bounds = serde_json::from_str::<serde_json::Value>(&document).unwrap();
+
+        assert_eq!(bounds[0]["requirement"], "^0.4");
+    }
+
+    #[test]
+    fn json_errors_and_warnings_are_one_object_a_line() {
+        let renderer = JsonRenderer {
+            named: false,
+            include_requirements: false,
+        };
+
+        for (line, key) in [
+            (renderer.render_error("missing", &missing()), "error"),
+            (
+                renderer.render_warning("missing", "cached a week ago"),
+                "warning",
+            ),
+        ] {
+            assert!(!line.contains('\n'), "{}", line);
+
+            let object = serde_json::from_str::<serde_json::Value>(&line).unwrap();
+
+            assert_eq!(object["name"], "missing");
+            assert!(object.get(key).is_some(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn toml_is_valid() {
+        let renderer = TomlRenderer {
+            include_requirements: true,
+        };
+
+        let document = render_all(&renderer);
+
+        let parsed = toml::from_str::<toml::Table>(&document).unwrap();
+
+        let crates = parsed["crates"].as_array().unwrap();
+
+        assert_eq!(crates.len(), 2);
+        assert_eq!(
+            crates[0]["versions"][0]["min_rust_version"].as_str(),
+            Some("1.70")
+        );
+        assert!(crates[0]["versions"][1].get("min_rust_version").is_none());
+        assert_eq!(crates[1]["requirement"].as_str(), Some("^0.4"));
+
+        for diagnostic in [
+            renderer.render_error("missing", &missing()),
+            renderer.render_warning("missing", "cached a week ago"),
+        ] {
+            let parsed = toml::from_str::<toml::Table>(&diagnostic).unwrap();
+
+            assert_eq!(parsed["crates"][0]["name"].as_str(), Some("missing"));
+        }
+    }
+
+    #[test]
+    fn junit_counts_and_escapes() {
+        let document = render_junit();
+
+        assert!(document.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+        assert!(document.contains("tests=\"3\" failures=\"0\" errors=\"1\""));
+        assert!(document.contains("missing &lt;&amp;&gt;"));
+        assert!(!document.contains("<&>"));
+        assert!(document.ends_with("</testsuite>"));
+    }
+
+    #[test]
+    fn text_and_plain_list_every_version() {
+        for renderer in [
+            &TextRenderer {
+                colored: false,
+                named: true,
+                annotated: true,
+            } as &dyn Renderer,
+            &PlainRenderer,
+        ] {
+            let document = render_all(renderer);
+
+            for version in ["0.4.2", "0.4.1", "0.4.0"] {
+                assert!(document.contains(version), "{}", document);
+            }
+
+            assert!(document.contains("^0.4"));
+        }
+    }
+
+    #[test]
+    fn text_errors_and_warnings_read_like_the_log() {
+        let renderer = TextRenderer {
+            colored: false,
+            named: true,
+            annotated: false,
+        };
+
+        assert_eq!(
+            renderer.render_error("missing", &missing()),
+            "Error: The crate missing <&> does not match any dependencies"
+        );
+        assert_eq!(
+            renderer.render_warning("missing", "cached a week ago"),
+            "Warning: cached a week ago"
+        );
+    }
+
+    #[test]
+    fn snapshots() {
+        let text = TextRenderer {
+            colored: false,
+            named: true,
+            annotated: true,
+        };
+
+        let json = JsonRenderer {
+            named: false,
+            include_requirements: false,
+        };
+
+        let toml = TomlRenderer {
+            include_requirements: true,
+        };
+
+        insta::assert_snapshot!("text", render_all(&text));
+        insta::assert_snapshot!("plain", render_all(&PlainRenderer));
+        insta::assert_snapshot!("json", render_json(&json));
+        insta::assert_snapshot!("toml", render_all(&toml));
+        insta::assert_snapshot!("junit", render_junit());
+    }
+}
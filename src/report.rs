@@ -0,0 +1,64 @@
+use semver::{Version, VersionReq};
+use serde::Serialize;
+
+use crate::provider::ParsedVersion;
+
+// Everything a run found out about one crate, the renderers and anything embedding the
+// crate work from this rather than from how it was found
+#[derive(Serialize)]
+pub struct CompatReport<'a> {
+    pub crate_name: &'a str,
+    pub bound: ReportBound<'a>,
+    // Newest first and already cut down to the ones asked for
+    pub versions: Vec<&'a ParsedVersion>,
+    pub dependents: Vec<ReportDependent<'a>>,
+    // The dependents whose requirements set the lower and upper ends of the bound
+    pub lower_dependent: ReportDependent<'a>,
+    pub upper_dependent: ReportDependent<'a>,
+    // The locked version the dependents were grouped by, when the crate is locked at
+    // more than one
+    pub locked: Option<&'a Version>,
+    // Warnings printed while the crate was being resolved
+    pub warnings: Vec<String>,
+    // Time since the run started
+    pub elapsed_ms: u128,
+}
+
+// The ends of the bound, both as merged from the requirements and as the oldest and
+// newest published versions within it before any filtering
+#[derive(Serialize)]
+pub struct ReportBound<'a> {
+    pub requirement: String,
+    pub oldest: &'a Version,
+    pub newest: &'a Version,
+}
+
+// What pinning a crate to its newest compatible version would change in the manifest
+#[derive(Serialize)]
+pub struct PinPreview<'a> {
+    pub name: &'a str,
+    pub version: String,
+    pub path: String,
+    pub up_to_date: bool,
+    // A unified diff of the change, empty when the pin is up to date
+    pub diff: String,
+}
+
+#[derive(Serialize)]
+pub struct ReportDependent<'a> {
+    pub name: &'a str,
+    pub version: &'a str,
+    pub requirement: String,
+}
+
+impl<'a> From<&'a ((String, String), VersionReq)> for ReportDependent<'a> {
+    fn from(dependent: &'a ((String, String), VersionReq)) -> Self {
+        let ((name, version), version_req) = dependent;
+
+        ReportDependent {
+            name,
+            version,
+            requirement: version_req.to_string(),
+        }
+    }
+}
@@ -1,11 +1,28 @@
+use log::{Level, LevelFilter};
 use owo_colors::OwoColorize;
-use std::{str::FromStr, time::Duration};
+use std::{
+    io::{IsTerminal, Write},
+    ops::{Add, Div, Mul, Sub},
+    process::Command,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering as AtomicOrdering},
+        Mutex,
+    },
+    time::Duration,
+};
 
-use crate::{error::ConstError, get_config};
+use crate::{
+    error::{ConstError, RustVersionError},
+    get_config, CONFIG,
+};
 
 pub const MAX_CACHE_AGE: u64 = 60 * 60 * 24 * 7; // 1 week
+pub const DEFAULT_TIMEOUT: u64 = 30; // seconds, as long as crates.io requests get
 pub const CRATE_NAME: &str = env!("CARGO_PKG_NAME");
 pub const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
 pub const MY_USER_AGENT: &str = "my-user-agent (the25thjohndoe@gmail.com)";
 
 pub fn print_header_and_items<I, T>(header: &str, items: I)
@@ -13,34 +30,317 @@ where
     I: IntoIterator<Item = T>,
     T: std::fmt::Display,
 {
-    println!("{}:", header.bold().cyan());
-    println!();
-    for item in items {
-        println!("{}", item.bold().blue());
+    print!("{}", header_and_items(header, items));
+}
+
+// For what explains the answer rather than being part of it, so that it stays out of the
+// json, toml and other output read from stdout
+pub fn eprint_header_and_items<I, T>(header: &str, items: I)
+where
+    I: IntoIterator<Item = T>,
+    T: std::fmt::Display,
+{
+    eprint!("{}", header_and_items(header, items));
+}
+
+fn header_and_items<I, T>(header: &str, items: I) -> String
+where
+    I: IntoIterator<Item = T>,
+    T: std::fmt::Display,
+{
+    let mut text = String::new();
+
+    if is_colored() {
+        text.push_str(&format!("{}:\n\n", header.bold().cyan()));
+        for item in items {
+            text.push_str(&format!("{}\n", item.bold().blue()));
+        }
+    } else {
+        text.push_str(&format!("{}:\n\n", header));
+        for item in items {
+            text.push_str(&format!("{}\n", item));
+        }
     }
+
+    text
 }
 
+// Errors are still printed when quiet, only without color for whatever reads them, the
+// config isn't set yet if reading it was what failed
 pub fn print_error(error: &ConstError) {
-    print!("{}: {}", "Error".bold().red(), error.bright_red());
+    if !is_colored() || CONFIG.get().is_some_and(|config| config.quiet) {
+        eprintln!("Error: {}", error);
+    } else {
+        eprintln!("{}: {}", "Error".bold().red(), error.bright_red());
+    }
 }
 
+// Kept for the report as well as logged, whether or not the logger prints it
 pub fn print_warning(message: &str) {
-    println!("{}: {}", "Warning".bold().yellow(), message.bright_yellow());
+    WARNINGS.lock().unwrap().push(message.to_string());
+
+    log::warn!("{}", message);
+}
+
+// Take the warnings printed since the last call, quiet or not
+pub fn take_warnings() -> Vec<String> {
+    std::mem::take(&mut WARNINGS.lock().unwrap())
 }
 
-pub fn print_info(message: &str) {
-    if get_config().verbose {
-        println!("{}: {}", "Info".bold().cyan(), message.bright_cyan());
+// How much detail -v, -vv and -vvv add, each level includes the ones before it. Logged
+// at the log level of the same name
+#[derive(Debug, Clone, Copy)]
+pub enum Verbosity {
+    // What was picked on the user's behalf, such as where the rust version came from
+    Info = 1,
+    // What was fetched and cached
+    Debug = 2,
+    // Every cache lookup
+    Trace = 3,
+}
+
+pub fn is_verbose(level: Verbosity) -> bool {
+    get_config().verbosity.ge(&(level as u8))
+}
+
+// The most detailed log level -v, -vv and -vvv let through, only warnings without them
+pub fn log_level_filter(verbosity: u8) -> LevelFilter {
+    match verbosity {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
     }
 }
 
-pub fn get_rust_version(version: &str) -> Option<(u64, u64, u64)> {
-    let mut places = version.split('.').map(|place| u64::from_str(place).ok());
-    Some((
-        places.next()??,
-        places.next().unwrap_or(Some(0))?,
-        places.next().unwrap_or(Some(0))?,
-    ))
+// Log records as warnings and info were always printed, to stdout apart from the results'
+// formatting. With RUST_LOG set it picks what is logged instead of -v, and records are
+// printed with a timestamp and the module they came from, such as when each request was
+// sent. Nothing is logged when quiet
+pub fn init_logger() {
+    let config = get_config();
+
+    let mut builder = env_logger::Builder::new();
+
+    builder.target(env_logger::Target::Stdout);
+
+    match std::env::var("RUST_LOG") {
+        _ if config.quiet => {
+            builder.filter_level(LevelFilter::Off);
+        }
+        Ok(filters) => {
+            builder.parse_filters(&filters).format_timestamp_millis();
+        }
+        Err(_) => {
+            // Only this crate's records, the ones from its dependencies are left to RUST_LOG
+            builder
+                .filter_level(LevelFilter::Off)
+                .filter_module(
+                    module_path!().split("::").next().unwrap_or_default(),
+                    log_level_filter(config.verbosity),
+                )
+                .format(|buf, record| {
+                    let message = record.args().to_string();
+
+                    match (record.level(), is_colored()) {
+                        (Level::Error | Level::Warn, true) => writeln!(
+                            buf,
+                            "{}: {}",
+                            "Warning".bold().yellow(),
+                            message.bright_yellow()
+                        ),
+                        (Level::Error | Level::Warn, false) => {
+                            writeln!(buf, "Warning: {}", message)
+                        }
+                        (_, true) => {
+                            writeln!(buf, "{}: {}", "Info".bold().cyan(), message.bright_cyan())
+                        }
+                        (_, false) => writeln!(buf, "Info: {}", message),
+                    }
+                });
+        }
+    }
+
+    // A logger set before this, by a program using the library, is kept
+    let _ = builder.try_init();
+}
+
+// The fewest single character insertions, deletions and substitutions that turn one
+// string into the other
+pub fn edit_distance(first: &str, second: &str) -> usize {
+    let second = second.chars().collect::<Vec<_>>();
+
+    // The distances from the part of `first` seen so far to each prefix of `second`
+    let mut previous = (0..=second.len()).collect::<Vec<_>>();
+
+    for (position, first_char) in first.chars().enumerate() {
+        let mut current = vec![position + 1];
+
+        for (index, second_char) in second.iter().enumerate() {
+            let substitution = previous[index] + usize::from(first_char.ne(second_char));
+
+            current.push(
+                substitution
+                    .min(previous[index + 1] + 1)
+                    .min(current[index] + 1),
+            );
+        }
+
+        previous = current;
+    }
+
+    previous[second.len()]
+}
+
+// Whether to color what is printed, decided as if by --color auto until the config is
+// set
+pub fn is_colored() -> bool {
+    match CONFIG.get() {
+        Some(config) => config.colored,
+        None => ColorChoice::Auto.is_colored(),
+    }
+}
+
+// When to color what is printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    // Only when printing to a terminal and NO_COLOR isn't set
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn is_colored(&self) -> bool {
+        match self {
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && std::io::stdout().is_terminal()
+            }
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = ConstError;
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            value => Err(ConstError::InvalidColorArgument {
+                argument: value.to_string(),
+            }),
+        }
+    }
+}
+
+// Read a rust version as (major, minor, patch), the parts left out are 0 so 1.70 and 1.70.0
+// compare the same. Channel suffixes like -nightly or -beta.1 are refused rather than
+// dropped, as such a toolchain isn't the stable release of the same number
+pub fn get_rust_version(version: &str) -> std::result::Result<(u64, u64, u64), RustVersionError> {
+    let version = version.trim();
+
+    if version.is_empty() {
+        return Err(RustVersionError::Empty);
+    }
+
+    if let Some(position) = version.find(['-', '+']) {
+        return Err(RustVersionError::ChannelSuffix(
+            version[position..].to_string(),
+        ));
+    }
+
+    let places = version
+        .split('.')
+        .map(|place| {
+            u64::from_str(place).map_err(|_| RustVersionError::InvalidPart(place.to_string()))
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    match places[..] {
+        [major] => Ok((major, 0, 0)),
+        [major, minor] => Ok((major, minor, 0)),
+        [major, minor, patch] => Ok((major, minor, patch)),
+        _ => Err(RustVersionError::TooManyParts(places.len())),
+    }
+}
+
+// Get the version of the rustc in use, e.g "1.80.0" from "rustc 1.80.0 (051478957 2024-07-21)",
+// channel suffixes like "-nightly" are dropped
+pub fn get_rustc_version() -> Option<String> {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+
+    let output = Command::new(rustc).arg("--version").output().ok()?;
+
+    let output = String::from_utf8(output.stdout).ok()?;
+
+    let version = output.split_whitespace().nth(1)?;
+
+    Some(version.split('-').next()?.to_string())
+}
+
+// The width of the bar drawn by `Progress`, in characters
+const PROGRESS_WIDTH: usize = 30;
+
+// A bar of how many of a known number of steps are done, redrawn in place on stderr as
+// each finishes and cleared once dropped. It is only drawn with --progress to a terminal,
+// and not under -vv or more as a line is then printed for each fetch anyway
+pub struct Progress {
+    label: &'static str,
+    total: usize,
+    done: AtomicUsize,
+    shown: bool,
+}
+
+impl Progress {
+    pub fn new(label: &'static str, total: usize) -> Progress {
+        let config = get_config();
+
+        let shown = config.progress
+            && !config.quiet
+            && !is_verbose(Verbosity::Debug)
+            && std::io::stdout().is_terminal()
+            && std::io::stderr().is_terminal()
+            && total.gt(&1);
+
+        Progress {
+            label,
+            total,
+            done: AtomicUsize::new(0),
+            shown,
+        }
+    }
+
+    pub fn advance(&self) {
+        let done = self.done.fetch_add(1, AtomicOrdering::Relaxed).add(1);
+
+        if !self.shown {
+            return;
+        }
+
+        let filled = done.mul(PROGRESS_WIDTH).div(self.total).min(PROGRESS_WIDTH);
+
+        eprint!(
+            "\r{} [{}{}] {}/{}",
+            self.label,
+            "=".repeat(filled),
+            " ".repeat(PROGRESS_WIDTH.sub(filled)),
+            done.min(self.total),
+            self.total
+        );
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if self.shown && self.done.load(AtomicOrdering::Relaxed).gt(&0) {
+            // Clear the line so what is printed next starts on a blank one
+            eprint!("\r\x1b[2K");
+        }
+    }
 }
 
 pub fn now_as_secs() -> u64 {
@@ -49,3 +349,263 @@ pub fn now_as_secs() -> u64 {
         .unwrap_or(Duration::from_secs(0))
         .as_secs()
 }
+
+// A calendar day, ordered chronologically
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    year: u32,
+    month: u32,
+    day: u32,
+}
+
+impl Date {
+    // The day of a timestamp such as "2024-01-01T12:00:00.000000+00:00", as crates.io
+    // gives for when a version was published
+    pub fn from_timestamp(value: &str) -> Option<Date> {
+        let (day, time) = value.split_at_checked(10)?;
+
+        if !time.is_empty() && !time.starts_with('T') {
+            return None;
+        }
+
+        day.parse().ok()
+    }
+}
+
+impl FromStr for Date {
+    type Err = ConstError;
+    // Expects exactly "YYYY-MM-DD"
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let error = || ConstError::InvalidDateArgument {
+            argument: value.to_string(),
+        };
+
+        let bytes = value.as_bytes();
+
+        let well_formed = bytes.len().eq(&10)
+            && bytes.iter().enumerate().all(|(index, byte)| match index {
+                4 | 7 => byte.eq(&b'-'),
+                _ => byte.is_ascii_digit(),
+            });
+
+        if !well_formed {
+            return Err(error());
+        }
+
+        // Only ASCII digits are left, so the numbers can't fail to parse
+        let number = |range: std::ops::Range<usize>| value[range].parse().unwrap_or_default();
+
+        let date = Date {
+            year: number(0..4),
+            month: number(5..7),
+            day: number(8..10),
+        };
+
+        if !(1..=12).contains(&date.month) || !(1..=31).contains(&date.day) {
+            return Err(error());
+        }
+
+        Ok(date)
+    }
+}
+
+// A length of time in seconds, written as a number followed by "s", "m", "h" or "d",
+// a bare number is in seconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ttl(pub u64);
+
+impl FromStr for Ttl {
+    type Err = ConstError;
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let error = || ConstError::InvalidCacheTtlArgument {
+            argument: value.to_string(),
+        };
+
+        let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+            Some(index) => value.split_at(index),
+            None => (value, "s"),
+        };
+
+        let multiplier = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 60 * 60 * 24,
+            _ => return Err(error()),
+        };
+
+        let number = number.parse::<u64>().map_err(|_| error())?;
+
+        number.checked_mul(multiplier).map(Ttl).ok_or_else(error)
+    }
+}
+
+// A number of seconds in the largest unit it makes at least one of, as --cache-ttl takes
+// them, such as 9d or 45m
+pub fn format_age(seconds: u64) -> String {
+    let (unit, size) = [("d", 60 * 60 * 24), ("h", 60 * 60), ("m", 60)]
+        .into_iter()
+        .find(|(_, size)| seconds.ge(size))
+        .unwrap_or(("s", 1));
+
+    format!("{}{}", seconds / size, unit)
+}
+
+// Escape the characters that can't appear as is in xml text or attributes
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            character => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+// Produce a single hunk unified diff between `old` and `new`, or an empty string if they
+// are the same
+pub fn unified_diff(path: &str, old: &str, new: &str) -> String {
+    const CONTEXT: usize = 3;
+
+    let old_lines = old.lines().collect::<Vec<_>>();
+    let new_lines = new.lines().collect::<Vec<_>>();
+
+    let prefix = old_lines
+        .iter()
+        .zip(new_lines.iter())
+        .take_while(|(a, b)| a.eq(b))
+        .count();
+
+    if prefix.eq(&old_lines.len()) && prefix.eq(&new_lines.len()) {
+        return String::new();
+    }
+
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a.eq(b))
+        .count();
+
+    let start = prefix.saturating_sub(CONTEXT);
+    let trailing = suffix.min(CONTEXT);
+    let old_end = old_lines.len() - suffix;
+    let new_end = new_lines.len() - suffix;
+
+    let mut diff = format!(
+        "--- {}\n+++ {}\n@@ -{},{} +{},{} @@\n",
+        path,
+        path,
+        start + 1,
+        old_end + trailing - start,
+        start + 1,
+        new_end + trailing - start
+    );
+
+    for line in &old_lines[start..prefix] {
+        diff.push_str(&format!(" {}\n", line));
+    }
+    for line in &old_lines[prefix..old_end] {
+        diff.push_str(&format!("-{}\n", line));
+    }
+    for line in &new_lines[prefix..new_end] {
+        diff.push_str(&format!("+{}\n", line));
+    }
+    for line in &old_lines[old_end..old_end + trailing] {
+        diff.push_str(&format!(" {}\n", line));
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: u32, month: u32, day: u32) -> Date {
+        Date { year, month, day }
+    }
+
+    #[test]
+    fn parses_exact_dates() {
+        assert_eq!("2024-01-31".parse::<Date>().unwrap(), date(2024, 1, 31));
+    }
+
+    #[test]
+    fn rejects_anything_but_a_date() {
+        for value in [
+            "2024-01-01garbage",
+            "2024-01-01T00:00:00Z",
+            "2024-1-01",
+            "2024-01-1",
+            "2024/01/01",
+            "+024-01-01",
+            "2024-13-01",
+            "2024-00-01",
+            "2024-01-32",
+            "",
+        ] {
+            assert!(value.parse::<Date>().is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn reads_the_day_of_a_timestamp() {
+        assert_eq!(
+            Date::from_timestamp("2024-01-31T12:00:00.000000+00:00"),
+            Some(date(2024, 1, 31))
+        );
+        assert_eq!(Date::from_timestamp("2024-01-31"), Some(date(2024, 1, 31)));
+        assert_eq!(Date::from_timestamp("2024-01-31garbage"), None);
+    }
+
+    #[test]
+    fn rust_versions_fill_in_missing_parts() {
+        assert_eq!(get_rust_version("1"), Ok((1, 0, 0)));
+        assert_eq!(get_rust_version("1.70"), Ok((1, 70, 0)));
+        assert_eq!(get_rust_version("1.70.0"), Ok((1, 70, 0)));
+        assert_eq!(get_rust_version(" 1.70.1 "), Ok((1, 70, 1)));
+        assert_eq!(get_rust_version("1.70"), get_rust_version("1.70.0"));
+    }
+
+    #[test]
+    fn rust_versions_say_why_they_are_refused() {
+        assert_eq!(
+            get_rust_version("1.70.0-nightly"),
+            Err(RustVersionError::ChannelSuffix("-nightly".to_string()))
+        );
+        assert_eq!(
+            get_rust_version("1.70.0-beta.1"),
+            Err(RustVersionError::ChannelSuffix("-beta.1".to_string()))
+        );
+        assert_eq!(get_rust_version(""), Err(RustVersionError::Empty));
+        assert_eq!(
+            get_rust_version("1.70.0.1"),
+            Err(RustVersionError::TooManyParts(4))
+        );
+        assert_eq!(
+            get_rust_version("1.x"),
+            Err(RustVersionError::InvalidPart("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn refused_rust_versions_name_the_reason() {
+        let error = ConstError::InvalidMaxRustVersionError {
+            version: "1.70.0-nightly".to_string(),
+            reason: RustVersionError::ChannelSuffix("-nightly".to_string()),
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "The max rust version 1.70.0-nightly is not valid, release channels like -nightly aren't supported, only stable versions"
+        );
+    }
+}
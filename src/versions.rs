@@ -0,0 +1,98 @@
+use cargo_lock::Lockfile;
+use clap::Parser;
+
+use crate::{
+    bound::{check_in_lockfile, find_dependents, merge_dependents, Bound},
+    error::{ConstError, Result},
+    provider::Provider,
+    utils::{print_header_and_items, print_warning},
+};
+
+/// Print every version of a crate, newest first, with whether it is yanked and the rust
+/// version it needs, before anything is filtered out
+#[derive(Parser)]
+pub struct Versions {
+    /// Also mark the versions outside the bound merged from the dependents in this
+    /// cargo.lock, to see why a version isn't listed by compat
+    #[clap(short, long)]
+    path: Option<String>,
+    /// Crate to print the versions of
+    crate_name: String,
+}
+
+impl Versions {
+    pub fn run(self) -> Result<()> {
+        let provider = Provider::new();
+
+        let bound = match &self.path {
+            Some(path) => self.merged_bound(&provider, path)?,
+            None => None,
+        };
+
+        let mut versions = provider.get_versions(&self.crate_name)?.versions;
+
+        versions.sort_by(|first, second| second.cmp(first));
+
+        let width = versions
+            .iter()
+            .map(|version| version.num.to_string().len())
+            .max()
+            .unwrap_or_default();
+
+        let lines = versions.iter().map(|version| {
+            let mut notes = Vec::new();
+
+            if version.yanked {
+                notes.push("yanked".to_string());
+            }
+
+            if let Some(rust_version) = &version.rust_version {
+                notes.push(format!("min-rust-version = {}", rust_version));
+            }
+
+            if bound
+                .as_ref()
+                .is_some_and(|bound| !bound.contains(&version.num))
+            {
+                notes.push("outside the bound".to_string());
+            }
+
+            format!(
+                "{:<width$}    {}",
+                version.num,
+                notes.join(", "),
+                width = width
+            )
+            .trim_end()
+            .to_string()
+        });
+
+        print_header_and_items(&format!("All versions of {}", self.crate_name), lines);
+
+        Ok(())
+    }
+
+    // Versions are still listed when the dependents don't agree on a bound, only without
+    // saying which are outside it
+    fn merged_bound(&self, provider: &Provider, path: &str) -> Result<Option<Bound>> {
+        let lock = Lockfile::load(path).map_err(|error| ConstError::CouldNotLoadLockFileError {
+            path: path.to_string(),
+            error,
+        })?;
+
+        check_in_lockfile(&self.crate_name, &lock)?;
+
+        let dependents = find_dependents(&self.crate_name, &lock);
+
+        match merge_dependents(provider, &self.crate_name, dependents) {
+            Ok(merged_bound) => Ok(Some(merged_bound.bound)),
+            Err(error) => {
+                print_warning(&format!(
+                    "{}\nNot showing which versions are outside it",
+                    error
+                ));
+                Ok(None)
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use cargo_lock::Lockfile;
+use clap::Parser;
+use semver::{Version, VersionReq};
+
+use crate::{
+    bound::{
+        check_in_lockfile, filter_locked_to, find_dependents, find_locked_versions,
+        merge_dependents, BoundEnd, MergedBound,
+    },
+    error::{ConstError, Result},
+    provider::Provider,
+    utils::print_header_and_items,
+};
+
+/// Print which dependents set the lower and upper ends of the range a crate must fall in
+#[derive(Parser)]
+pub struct Why {
+    /// Path to cargo.lock
+    #[clap(short, long, default_value = "Cargo.lock")]
+    path: String,
+    /// Crate to explain the range of
+    crate_name: String,
+}
+
+impl Why {
+    pub fn run(self) -> Result<()> {
+        let lock =
+            Lockfile::load(&self.path).map_err(|error| ConstError::CouldNotLoadLockFileError {
+                path: self.path.clone(),
+                error,
+            })?;
+
+        let provider = Provider::new();
+
+        check_in_lockfile(&self.crate_name, &lock)?;
+
+        let dependents = find_dependents(&self.crate_name, &lock);
+
+        // Dependents locked to different versions of the crate were resolved apart from
+        // each other, so each group has its own range, as with compat
+        let locked_versions = find_locked_versions(&self.crate_name, &lock);
+
+        if locked_versions.len().le(&1) {
+            let merged_bound = merge_dependents(&provider, &self.crate_name, dependents)?;
+
+            self.display(&merged_bound, None);
+
+            return Ok(());
+        }
+
+        for (position, locked) in locked_versions.iter().enumerate() {
+            let group = filter_locked_to(dependents.clone(), &lock, &self.crate_name, locked);
+
+            if group.is_empty() {
+                continue;
+            }
+
+            let merged_bound = merge_dependents(&provider, &self.crate_name, group)?;
+
+            if position.gt(&0) {
+                println!();
+            }
+
+            self.display(&merged_bound, Some(locked));
+        }
+
+        Ok(())
+    }
+
+    fn display(&self, merged_bound: &MergedBound, locked: Option<&Version>) {
+        let header = match locked {
+            Some(locked) => format!(
+                "Range of {} for the dependents locked to {}",
+                self.crate_name, locked
+            ),
+            None => format!("Range of {}", self.crate_name),
+        };
+
+        let requirement = VersionReq::from(&merged_bound.bound);
+
+        print_header_and_items(&header, [requirement]);
+
+        println!();
+
+        let set_by = |index: usize| {
+            let ((name, version), version_req) = &merged_bound.dependents[index];
+
+            format!("{} {}, which requires {}", name, version, version_req)
+        };
+
+        let lower = &merged_bound.bound.lower;
+
+        println!(
+            "lower end {}{} is set by {}",
+            if lower.inclusive { ">=" } else { ">" },
+            lower.version,
+            set_by(merged_bound.lower_index)
+        );
+
+        match &merged_bound.bound.upper {
+            BoundEnd::Unbounded => println!("upper end is open, no dependent sets one"),
+            BoundEnd::Inclusive(version) => println!(
+                "upper end <={} is set by {}",
+                version,
+                set_by(merged_bound.upper_index)
+            ),
+            BoundEnd::Exclusive(version) => println!(
+                "upper end <{} is set by {}",
+                version,
+                set_by(merged_bound.upper_index)
+            ),
+        }
+    }
+}